@@ -50,24 +50,39 @@ fn build_cli() -> clap::Command {
              and resource-constrained environments.",
         )
         .arg(
-            Arg::new("input")
-                .help("Input PNG file")
-                .required(true)
+            Arg::new("inputs")
+                .help("Input PNG files, directories or glob patterns")
+                .required_unless_present("manifest")
+                .num_args(1..)
                 .value_name("INPUT"),
         )
         .arg(
-            Arg::new("output")
-                .short('o')
-                .long("output")
-                .help("Output C file (defaults to input filename with .c extension)")
-                .value_name("OUTPUT"),
+            Arg::new("manifest")
+                .short('m')
+                .long("manifest")
+                .help("Convert every image listed in a TOML asset manifest")
+                .value_name("FILE")
+                .conflicts_with_all(["inputs", "output", "stdout", "var-name"]),
         )
         .arg(
-            Arg::new("stdout")
-                .long("stdout")
-                .help("Write to stdout instead of file")
+            Arg::new("watch")
+                .short('w')
+                .long("watch")
+                .help(
+                    "Keep running and convert inputs again whenever they or the manifest \
+                     change (implies --overwrite)",
+                )
+                .action(ArgAction::SetTrue)
+                .conflicts_with("stdout"),
+        )
+        .arg(
+            Arg::new("recursive")
+                .short('r')
+                .long("recursive")
+                .help("Also convert PNGs in subdirectories of input directories")
                 .action(ArgAction::SetTrue),
         )
+        .args(output_args())
         .arg(
             Arg::new("format")
                 .short('f')
@@ -80,6 +95,13 @@ fn build_cli() -> clap::Command {
                     "true-color",
                     "true-color-alpha",
                     "true-color-chroma",
+                    "rgb565-swapped",
+                    "argb8565",
+                    "argb8888",
+                    "xrgb8888",
+                    "rgb888",
+                    "l8",
+                    "al88",
                     "indexed1",
                     "indexed2",
                     "indexed4",
@@ -115,6 +137,143 @@ fn build_cli() -> clap::Command {
                 .help("Generate big-endian RGB565 (for big-endian systems)")
                 .action(ArgAction::SetTrue),
         )
+        .args(processing_args())
+}
+
+/// Arguments controlling where and how the result is written.
+fn output_args() -> Vec<clap::Arg> {
+    use clap::{Arg, ArgAction};
+
+    vec![
+        Arg::new("output")
+            .short('o')
+            .long("output")
+            .help("Output file (defaults to input filename with .c or .bin extension)")
+            .value_name("OUTPUT"),
+        Arg::new("jobs")
+            .short('j')
+            .long("jobs")
+            .help("Number of files converted in parallel (defaults to the number of CPUs)")
+            .value_name("N"),
+        Arg::new("cache")
+            .long("cache")
+            .help(
+                "Remember conversions in this file and skip inputs whose PNG and options \
+                 did not change since",
+            )
+            .value_name("FILE"),
+        Arg::new("output-dir")
+            .long("output-dir")
+            .help("Write outputs into this directory, mirroring the input tree")
+            .value_name("DIR")
+            .conflicts_with_all(["output", "stdout"]),
+        Arg::new("bin")
+            .long("bin")
+            .help("Write an LVGL binary image file (.bin) instead of a C array")
+            .action(ArgAction::SetTrue)
+            .conflicts_with("all-depths"),
+        Arg::new("header")
+            .long("header")
+            .help("Also write a companion .h declaring the image next to the .c file")
+            .action(ArgAction::SetTrue)
+            .conflicts_with_all(["stdout", "bin"]),
+        Arg::new("var-name")
+            .long("var-name")
+            .help("C variable name (defaults to the output file name, sanitized)")
+            .value_name("NAME"),
+        Arg::new("var-prefix")
+            .long("var-prefix")
+            .help("Prefix for the C variable name (e.g. img_)")
+            .value_name("PREFIX")
+            .default_value(""),
+        Arg::new("stdout")
+            .long("stdout")
+            .help("Write to stdout instead of file")
+            .action(ArgAction::SetTrue),
+    ]
+}
+
+/// Arguments controlling how pixels are reduced to the target format.
+fn processing_args() -> Vec<clap::Arg> {
+    use clap::Arg;
+
+    vec![
+        Arg::new("quantize")
+            .long("quantize")
+            .help("Color quantization algorithm for indexed formats with too many colors")
+            .value_name("QUANTIZE")
+            .default_value("median-cut")
+            .value_parser(["median-cut", "octree", "k-means"]),
+        Arg::new("dither")
+            .long("dither")
+            .help("Dithering applied before reducing color or alpha depth")
+            .value_name("DITHER")
+            .default_value("none")
+            .value_parser([
+                "none",
+                "floyd-steinberg",
+                "atkinson",
+                "bayer4",
+                "bayer8",
+                "blue-noise",
+            ]),
+        Arg::new("chroma-key")
+            .long("chroma-key")
+            .help(
+                "Chroma key color as RRGGBB hex (true-color-chroma only; derived from \
+                 fully transparent pixels if omitted)",
+            )
+            .value_name("RRGGBB"),
+        Arg::new("all-depths")
+            .long("all-depths")
+            .help(
+                "Emit every LV_COLOR_DEPTH variant of true color formats inside \
+                 preprocessor guards (LVGL 8.x only)",
+            )
+            .action(clap::ArgAction::SetTrue)
+            .requires("lvgl-v8"),
+        Arg::new("alpha-source")
+            .long("alpha-source")
+            .help(
+                "Channel that alpha-only formats read coverage from (alpha channel if \
+                 the image has one, luminance otherwise)",
+            )
+            .value_name("ALPHA_SOURCE")
+            .default_value("auto")
+            .value_parser([
+                "auto",
+                "alpha",
+                "luminance",
+                "inverted-luminance",
+                "red",
+                "green",
+                "blue",
+            ]),
+        Arg::new("stride-align")
+            .long("stride-align")
+            .help("Pad every row to a multiple of this many bytes (power of two, LVGL 9.x only)")
+            .value_name("BYTES")
+            .default_value("1")
+            .conflicts_with("lvgl-v8"),
+        Arg::new("premultiply")
+            .long("premultiply")
+            .help(
+                "Store color premultiplied by alpha (true-color-alpha, argb8565, \
+                 argb8888; LVGL 9.x only)",
+            )
+            .action(clap::ArgAction::SetTrue)
+            .conflicts_with("lvgl-v8"),
+        Arg::new("compress")
+            .long("compress")
+            .help(
+                "Compress the image data (LVGL 9.x only; needs LV_USE_RLE or \
+                 LV_USE_LZ4 at runtime)",
+            )
+            .value_name("COMPRESS")
+            .default_value("none")
+            .value_parser(["none", "rle", "lz4"])
+            .conflicts_with("lvgl-v8"),
+    ]
 }
 
 const MANPAGE_EXTRA_SECTIONS: &str = r"
@@ -127,6 +286,48 @@ Convert a PNG with automatic format detection (LVGL 9.x):
 Creates \fBicon.c\fR with automatically detected format using LVGL 9.x constants.
 .RE
 .TP
+Convert a whole asset tree:
+.B png2lvgl assets/ \-r \-\-output-dir src/images
+.PP
+.RS
+Inputs may be files, directories or glob patterns such as \fBicons/*.png\fR
+(quoted so the shell leaves them alone). The output directory mirrors the
+input tree. Files are converted in parallel on all CPUs (limit with
+\fB\-j\fR); each file's log lines are printed together. Failing files are
+listed in a summary at the end instead of stopping the batch.
+.RE
+.TP
+Regenerate every asset of a project:
+.B png2lvgl \-\-manifest assets.toml
+.PP
+.RS
+The manifest has an optional \fB[defaults]\fR table and one \fB[[image]]\fR
+table per image. Keys are named after the long options without the leading dashes
+(\fBinput\fR, \fBoutput\fR, \fBoutput-dir\fR, \fBformat\fR,
+\fBvar-name\fR, \fBbig-endian\fR, ...); the LVGL version is \fBlvgl = v8\fR
+or \fBv9\fR. Entries override the defaults, which override the command line.
+Paths are relative to the manifest. Unknown keys are rejected.
+.RE
+.TP
+Skip unchanged assets in large batches:
+.B png2lvgl assets/ \-r \-\-output-dir src/images \-\-overwrite \-\-cache .png2lvgl-cache
+.PP
+.RS
+Outputs whose content would not change are never rewritten, so their
+timestamps stay put and build systems do not recompile them. With a cache
+file, inputs whose PNG bytes and options match the last run (and whose
+outputs were not modified) are not even decoded.
+.RE
+.TP
+Regenerate assets while editing them:
+.B png2lvgl \-\-manifest assets.toml \-\-watch
+.PP
+.RS
+Converts everything once, then polls the inputs (and the manifest) and converts
+the files that changed once they have been quiet for half a second. Errors
+are reported and watching continues.
+.RE
+.TP
 Target LVGL 8.x:
 .B png2lvgl icon.png \-\-lvgl-v8
 .PP
@@ -134,11 +335,100 @@ Target LVGL 8.x:
 Generates output compatible with LVGL 8.x using LV_IMG_CF_* constants.
 .RE
 .TP
-Use 4-bit indexed grayscale:
+Use a 4-bit indexed palette:
 .B png2lvgl logo.png \-f indexed4
 .PP
 .RS
-Converts to 16-color grayscale palette, ideal for small icons.
+Builds a palette of up to 16 colors from the image, ideal for small icons.
+.RE
+.TP
+Reduce a full-color image to 256 colors with k-means:
+.B png2lvgl photo.png \-f indexed8 \-\-quantize k-means
+.PP
+.RS
+Images with more colors than the indexed format allows are quantized. The
+quantization error is reported in the header comment.
+.RE
+.TP
+Reduce banding on a 16-bit panel:
+.B png2lvgl gradient.png \-f true-color \-\-dither floyd-steinberg
+.PP
+.RS
+Dithering (Floyd-Steinberg, Atkinson, ordered Bayer 4x4/8x8 or blue noise) is
+applied before RGB565 encoding, palette mapping or alpha bit packing.
+.RE
+.TP
+Replace transparent pixels with a chroma key:
+.B png2lvgl sprite.png \-f true-color-chroma \-\-chroma-key 00ff00
+.PP
+.RS
+Without \fB\-\-chroma\-key\fR the key is derived from the fully transparent
+pixels. A warning is printed if opaque pixels collide with the key after
+RGB565 rounding. For LVGL 8.x the key must match LV_COLOR_CHROMA_KEY. LVGL 9.x
+has no chroma-keyed format, so the data is tagged as plain RGB565.
+.RE
+.TP
+One LVGL 8.x asset for any color depth:
+.B png2lvgl icon.png \-\-lvgl-v8 \-\-all-depths
+.PP
+.RS
+Emits RGB332, RGB565, byte-swapped RGB565 and ARGB8888 data inside
+\fB#if LV_COLOR_DEPTH\fR guards, like the classic LVGL 8 converter. The
+descriptor's data_size is computed from LV_COLOR_SIZE at compile time.
+.RE
+.TP
+Turn an icon with transparency into a 4-bit mask:
+.B png2lvgl icon.png \-f alpha4
+.PP
+.RS
+Alpha formats read the alpha channel when the image has one and luminance
+otherwise. Use \fB\-\-alpha\-source\fR to pick luminance,
+inverted-luminance or a single red, green or blue channel instead.
+.RE
+.TP
+Align rows for DMA2D or PXP:
+.B png2lvgl background.png \-f argb8888 \-\-stride-align 64
+.PP
+.RS
+Pads every row with zero bytes so the stride is a multiple of the given power
+of two. The padded stride is written to header.stride and included in
+data_size. Only available for LVGL 9.x.
+.RE
+.TP
+Premultiplied alpha for faster blending:
+.B png2lvgl overlay.png \-f argb8888 \-\-premultiply
+.PP
+.RS
+Multiplies every color channel by its alpha before encoding and sets
+LV_IMAGE_FLAGS_PREMULTIPLIED in the descriptor. Applies to
+true-color-alpha, argb8565 and argb8888. Only available for LVGL 9.x.
+.RE
+.TP
+Compress a background image:
+.B png2lvgl background.png \-f true-color \-\-compress lz4
+.PP
+.RS
+Wraps the pixel data in LVGL's compressed-image header and sets
+LV_IMAGE_FLAGS_COMPRESSED. RLE needs \fBLV_USE_RLE\fR, LZ4 needs
+\fBLV_USE_LZ4_INTERNAL\fR or \fBLV_USE_LZ4_EXTERNAL\fR. The header comment
+reports the compression ratio. Only available for LVGL 9.x.
+.RE
+.TP
+Binary image for a file system:
+.B png2lvgl icon.png \-\-bin
+.PP
+.RS
+Creates \fBicon.bin\fR with the LVGL 8.x or 9.x image header followed by the
+same pixel data as the C array, for loading through an LVGL file system
+driver (e.g. \fBS:/icon.bin\fR).
+.RE
+.TP
+Generate a companion header:
+.B png2lvgl icon.png \-\-header
+.PP
+.RS
+Also writes \fBicon.h\fR with an include guard, the extern declaration of
+the image descriptor and ICON_WIDTH, ICON_HEIGHT and ICON_CF defines.
 .RE
 .TP
 Output to stdout:
@@ -157,7 +447,8 @@ Color palette data (for indexed formats)
 .IP \(bu 2
 Pixel data array
 .IP \(bu 2
-Image descriptor structure (lv_img_dsc_t)
+Image descriptor structure (lv_image_dsc_t with magic, flags and stride for
+LVGL 9.x; lv_img_dsc_t for LVGL 8.x)
 .SH FORMAT SELECTION GUIDE
 .TP
 .B True Color (RGB565)
@@ -165,9 +456,32 @@ Use for full-color images, photos, or complex graphics. 16-bit per pixel.
 .TP
 .B True Color Alpha
 Use when transparency is needed with full color. 24-bit per pixel.
+LVGL 9.x stores a separate alpha plane (RGB565A8); LVGL 8.x stores the alpha
+byte after each pixel's color bytes.
+.TP
+.B True Color Chroma
+Use for images with hard-edged transparency on targets without alpha blending.
+Pixels below 50% alpha are replaced by the chroma key. 16-bit per pixel.
+.TP
+.B RGB565 Swapped
+Use for SPI displays that expect the two RGB565 bytes swapped
+(LV_COLOR_16_SWAP in LVGL 8.x, RGB565_SWAPPED in LVGL 9.x). 16-bit per pixel.
+.TP
+.B ARGB8565
+RGB565 color with the alpha byte interleaved after each pixel. 24-bit per pixel.
+.TP
+.B ARGB8888 / XRGB8888 / RGB888
+Use for 24-bit displays and GPU-backed targets. Stored in LVGL's B, G, R(, A)
+byte order; 32 or 24-bit per pixel. RGB888 requires LVGL 9.x; with LVGL 8.x the
+32-bit formats require LV_COLOR_DEPTH 32.
+.TP
+.B L8 / AL88
+Use for monochrome displays and grayscale UIs. 8-bit luminance, optionally with
+8-bit alpha (LVGL 9.x only). Grayscale PNGs select these automatically.
 .TP
 .B Indexed (1/2/4/8-bit)
 Use for icons, logos, or images with limited colors. Saves memory with palette-based encoding.
+Images with more colors are reduced with median-cut (default), octree or k-means quantization.
 .TP
 .B Alpha Only (1/2/4/8-bit)
 Use for masks, monochrome icons, or alpha-only overlays. Most memory-efficient.
@@ -184,11 +498,14 @@ Use \fB\-\-lvgl-v8\fR flag for LVGL 8.x projects, or omit for LVGL 9.x (default)
 .IP \(bu 2
 The tool preserves image dimensions in the output
 .IP \(bu 2
-Indexed formats automatically convert images to grayscale
+Indexed formats build their palette from the unique colors of the image
 .IP \(bu 2
-Alpha-only formats extract only the alpha channel
+Alpha-only formats extract the alpha channel, or the channel chosen with \fB\-\-alpha\-source\fR
 .IP \(bu 2
-Output files use proper C naming conventions (underscores for hyphens)
+Variable names are sanitized into valid C identifiers: spaces, dots, dashes
+and non-ASCII characters become underscores, a leading digit gets an img_
+prefix and C/C++ keywords get a trailing underscore. Names given with
+\fB\-\-var\-name\fR are used as-is and rejected if invalid.
 .SH BUGS
 Report bugs at: https://github.com/metaneutrons/png2lvgl/issues
 .SH AUTHOR
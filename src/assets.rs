@@ -0,0 +1,302 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Fabian Schmieder
+
+use std::collections::HashSet;
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use image::GenericImageView;
+use tracing::debug;
+
+use crate::convert::{self, ConvertOptions};
+use crate::error::{Png2LvglError, Result};
+use crate::{identifier, validation};
+
+/// Rust file written by [`Emit::Rust`].
+pub const RUST_FILE: &str = "lvgl_assets.rs";
+
+/// What [`Assets::build`] writes into the output directory.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Emit {
+    /// A `.c` file and companion `.h` per image, to compile with e.g. the
+    /// `cc` crate.
+    #[default]
+    C,
+    /// [`RUST_FILE`] with a `static` byte array per image, holding an LVGL
+    /// binary image (header followed by pixel data), to `include!`.
+    Rust,
+}
+
+/// Converts images at build time, from a `build.rs`.
+///
+/// Images added with [`asset`](Self::asset) use the shared options and a
+/// variable name derived from the file name; [`asset_with`](Self::asset_with)
+/// takes options of its own. `cargo:rerun-if-changed` is printed for every
+/// image.
+///
+/// ```no_run
+/// // build.rs
+/// use png2lvgl::{Assets, ColorFormat, ConvertOptions, Emit};
+///
+/// let mut icon = ConvertOptions::new("icon_wifi");
+/// icon.format = ColorFormat::Alpha4;
+///
+/// Assets::new()
+///     .asset("assets/logo.png")
+///     .asset_with("assets/wifi.png", icon)
+///     .emit(Emit::Rust)
+///     .build()?;
+/// # Ok::<(), png2lvgl::Png2LvglError>(())
+/// ```
+///
+/// The crate then includes the generated statics:
+///
+/// ```ignore
+/// include!(concat!(env!("OUT_DIR"), "/lvgl_assets.rs"));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Assets {
+    options: ConvertOptions,
+    images: Vec<(PathBuf, Option<ConvertOptions>)>,
+    out_dir: Option<PathBuf>,
+    emit: Emit,
+}
+
+impl Assets {
+    /// A builder with the command line's default options, writing C.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Options for images added with [`asset`](Self::asset); their variable
+    /// name is ignored.
+    #[must_use]
+    pub fn options(mut self, options: ConvertOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Convert `path` with the shared options, naming the variable after the
+    /// file (`logo-dark.png` becomes `logo_dark`).
+    #[must_use]
+    pub fn asset(mut self, path: impl Into<PathBuf>) -> Self {
+        self.images.push((path.into(), None));
+        self
+    }
+
+    /// Convert `path` with its own options, including the variable name.
+    #[must_use]
+    pub fn asset_with(mut self, path: impl Into<PathBuf>, options: ConvertOptions) -> Self {
+        self.images.push((path.into(), Some(options)));
+        self
+    }
+
+    /// Write into `dir` instead of `OUT_DIR`.
+    #[must_use]
+    pub fn out_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.out_dir = Some(dir.into());
+        self
+    }
+
+    /// Select C sources or Rust statics.
+    #[must_use]
+    pub const fn emit(mut self, emit: Emit) -> Self {
+        self.emit = emit;
+        self
+    }
+
+    /// Convert every image and print `cargo:rerun-if-changed` for it.
+    ///
+    /// Returns the `.c` files for [`Emit::C`], or the Rust file for
+    /// [`Emit::Rust`].
+    ///
+    /// # Errors
+    ///
+    /// Fails if `OUT_DIR` is not set and no output directory was given, if
+    /// two images get variable names that differ only in case, and if an
+    /// image cannot be read, converted or written.
+    pub fn build(&self) -> Result<Vec<PathBuf>> {
+        let out_dir = match &self.out_dir {
+            Some(dir) => dir.clone(),
+            None => env::var_os("OUT_DIR").map(PathBuf::from).ok_or_else(|| {
+                Png2LvglError::Config(
+                    "OUT_DIR is not set; call out_dir() outside build scripts".to_string(),
+                )
+            })?,
+        };
+
+        let mut names = HashSet::new();
+        let mut written = Vec::new();
+        let mut statics = String::new();
+        for (path, options) in &self.images {
+            println!("cargo:rerun-if-changed={}", path.display());
+            let options = self.asset_options(path, options.as_ref());
+            // Compared as emitted in Rust statics and header guards, which
+            // also keeps file names apart on case-insensitive file systems
+            let upper_name = options.var_name.to_uppercase();
+            if !names.insert(upper_name.clone()) {
+                return Err(Png2LvglError::Config(format!(
+                    "{}: variable name '{}' is already used by another asset (as {upper_name})",
+                    path.display(),
+                    options.var_name
+                )));
+            }
+
+            validation::validate_input_file(path)?;
+            let img = image::open(path)?;
+            debug!(input = %path.display(), var_name = options.var_name, "Converting asset");
+
+            match self.emit {
+                Emit::C => {
+                    let c_file = out_dir.join(&options.output_file);
+                    fs::write(&c_file, convert::convert(&img, &options)?)?;
+                    fs::write(
+                        c_file.with_extension("h"),
+                        convert::convert_header(&img, &options)?,
+                    )?;
+                    written.push(c_file);
+                }
+                Emit::Rust => {
+                    let bin_file = out_dir.join(format!("{}.bin", options.var_name));
+                    let data = convert::convert_bin(&img, &options)?;
+                    fs::write(&bin_file, &data)?;
+
+                    let (w, h) = img.dimensions();
+                    let format = options.resolve_format(&img);
+                    let _ = writeln!(
+                        statics,
+                        "/// `{}`: {w}x{h}, `{}`\npub static {}: &[u8; {}] = include_bytes!({:?});",
+                        options.source_file,
+                        format.lvgl_const(options.lvgl_version),
+                        upper_name,
+                        data.len(),
+                        bin_file.display().to_string(),
+                    );
+                }
+            }
+        }
+
+        if self.emit == Emit::Rust {
+            let rust_file = out_dir.join(RUST_FILE);
+            fs::write(&rust_file, statics)?;
+            written.push(rust_file);
+        }
+        Ok(written)
+    }
+
+    /// Options for one image, with the file names filled in.
+    fn asset_options(&self, path: &Path, options: Option<&ConvertOptions>) -> ConvertOptions {
+        let mut options = options.cloned().unwrap_or_else(|| {
+            let stem = path
+                .file_stem()
+                .map_or_else(|| "image".into(), |s| s.to_string_lossy());
+            ConvertOptions {
+                var_name: identifier::sanitize(&stem),
+                ..self.options.clone()
+            }
+        });
+        options.source_file = path
+            .file_name()
+            .map_or_else(|| "image.png".into(), |s| s.to_string_lossy().into_owned());
+        options.output_file = format!("{}.c", options.var_name);
+        options
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::DynamicImage;
+
+    use super::*;
+    use crate::format::ColorFormat;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("png2lvgl-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn writes_c_sources_and_headers() {
+        let dir = temp_dir("assets-c");
+        DynamicImage::new_rgba8(4, 2)
+            .save(dir.join("logo-dark.png"))
+            .unwrap();
+
+        let written = Assets::new()
+            .asset(dir.join("logo-dark.png"))
+            .out_dir(&dir)
+            .build()
+            .unwrap();
+
+        assert_eq!(written, [dir.join("logo_dark.c")]);
+        let c_source = fs::read_to_string(&written[0]).unwrap();
+        assert!(c_source.contains("const lv_image_dsc_t logo_dark = {"));
+        assert!(dir.join("logo_dark.h").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn writes_rust_statics() {
+        let dir = temp_dir("assets-rust");
+        DynamicImage::new_luma8(8, 8)
+            .save(dir.join("mask.png"))
+            .unwrap();
+        let mut options = ConvertOptions::new("wifi");
+        options.format = ColorFormat::Alpha4;
+
+        let written = Assets::new()
+            .asset_with(dir.join("mask.png"), options)
+            .out_dir(&dir)
+            .emit(Emit::Rust)
+            .build()
+            .unwrap();
+
+        assert_eq!(written, [dir.join(RUST_FILE)]);
+        let rust = fs::read_to_string(&written[0]).unwrap();
+        assert!(rust.contains("/// `mask.png`: 8x8, `LV_COLOR_FORMAT_A4`"));
+        assert!(rust.contains("pub static WIFI: &[u8; 44] = include_bytes!("));
+        assert_eq!(fs::read(dir.join("wifi.bin")).unwrap().len(), 44);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rejects_duplicate_names() {
+        let dir = temp_dir("assets-duplicate");
+        for subdir in ["a", "b"] {
+            fs::create_dir(dir.join(subdir)).unwrap();
+            DynamicImage::new_rgb8(1, 1)
+                .save(dir.join(subdir).join("logo.png"))
+                .unwrap();
+        }
+
+        let result = Assets::new()
+            .asset(dir.join("a/logo.png"))
+            .asset(dir.join("b/logo.png"))
+            .out_dir(&dir)
+            .build();
+
+        let Err(Png2LvglError::Config(message)) = result else {
+            panic!("expected a configuration error, got {result:?}");
+        };
+        assert!(message.contains("variable name 'logo' is already used by another asset"));
+
+        // Both would be emitted as `pub static LOGO`
+        let result = Assets::new()
+            .asset(dir.join("a/logo.png"))
+            .asset_with(dir.join("b/logo.png"), ConvertOptions::new("Logo"))
+            .out_dir(&dir)
+            .emit(Emit::Rust)
+            .build();
+
+        let Err(Png2LvglError::Config(message)) = result else {
+            panic!("expected a configuration error, got {result:?}");
+        };
+        assert!(message.contains("variable name 'Logo' is already used by another asset"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,205 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Fabian Schmieder
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use tracing::{debug, warn};
+
+use crate::Args;
+use png2lvgl::{Png2LvglError, Result};
+
+/// Characters that make an input argument a glob pattern.
+const GLOB_CHARS: &[char] = &['*', '?', '['];
+
+/// A PNG to convert.
+pub struct InputFile {
+    pub path: PathBuf,
+    /// Path below the directory or glob base it was found under, used to
+    /// mirror the input tree in the output directory.
+    pub relative: PathBuf,
+}
+
+/// A file to convert with the options that apply to it.
+pub struct Job {
+    pub args: Args,
+    pub file: InputFile,
+}
+
+/// Expand files, directories and glob patterns into the PNGs to convert.
+///
+/// Files are kept in argument order; directory and glob matches are sorted.
+/// Plain file arguments are passed through unchecked so that missing files
+/// show up as conversion failures.
+pub fn collect_inputs(inputs: &[PathBuf], recursive: bool) -> Result<Vec<InputFile>> {
+    let mut files = Vec::new();
+
+    for input in inputs {
+        let found = expand(input, recursive)?;
+        if found.is_empty() {
+            warn!(input = %input.display(), "No PNG files found");
+        }
+        files.extend(found);
+    }
+
+    let mut seen = HashSet::new();
+    files.retain(|file| seen.insert(file.path.clone()));
+    debug!(count = files.len(), "Collected input files");
+    Ok(files)
+}
+
+/// PNGs named by one input argument.
+pub fn expand(input: &Path, recursive: bool) -> Result<Vec<InputFile>> {
+    let pattern = input.to_string_lossy();
+    if input.is_dir() {
+        let mut paths = Vec::new();
+        walk(input, recursive, &mut paths)?;
+        Ok(relative_to(input, paths))
+    } else if pattern.contains(GLOB_CHARS) {
+        expand_glob(&pattern)
+    } else {
+        Ok(vec![InputFile {
+            path: input.to_path_buf(),
+            relative: input.file_name().map(PathBuf::from).unwrap_or_default(),
+        }])
+    }
+}
+
+/// Output path for `file`: next to the input, or mirrored below `output_dir`.
+pub fn output_path(file: &InputFile, output_dir: Option<&Path>, extension: &str) -> PathBuf {
+    output_dir.map_or_else(
+        || file.path.with_extension(extension),
+        |dir| dir.join(&file.relative).with_extension(extension),
+    )
+}
+
+fn is_png(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("png"))
+}
+
+fn walk(dir: &Path, recursive: bool, paths: &mut Vec<PathBuf>) -> Result<()> {
+    let mut entries = fs::read_dir(dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort();
+
+    for path in entries {
+        if path.is_dir() {
+            if recursive {
+                walk(&path, recursive, paths)?;
+            }
+        } else if is_png(&path) {
+            paths.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn expand_glob(pattern: &str) -> Result<Vec<InputFile>> {
+    let paths = glob::glob(pattern)
+        .map_err(|e| Png2LvglError::Config(format!("Invalid glob pattern '{pattern}': {e}")))?
+        .filter_map(|entry| match entry {
+            Ok(path) => Some(path),
+            Err(e) => {
+                warn!("Skipping unreadable path: {e}");
+                None
+            }
+        })
+        .filter(|path| path.is_file() && is_png(path))
+        .collect();
+
+    Ok(relative_to(&glob_base(pattern), paths))
+}
+
+/// Leading directories of a glob pattern that contain no wildcards.
+fn glob_base(pattern: &str) -> PathBuf {
+    let mut base = PathBuf::new();
+    let parent = Path::new(pattern).parent();
+    for component in parent.iter().flat_map(|p| p.components()) {
+        if let Component::Normal(part) = component
+            && part.to_string_lossy().contains(GLOB_CHARS)
+        {
+            break;
+        }
+        base.push(component);
+    }
+    base
+}
+
+fn relative_to(base: &Path, paths: Vec<PathBuf>) -> Vec<InputFile> {
+    paths
+        .into_iter()
+        .map(|path| {
+            let relative = path.strip_prefix(base).map_or_else(
+                |_| path.file_name().map(PathBuf::from).unwrap_or_default(),
+                Path::to_path_buf,
+            );
+            InputFile { path, relative }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(path: &str, relative: &str) -> InputFile {
+        InputFile {
+            path: PathBuf::from(path),
+            relative: PathBuf::from(relative),
+        }
+    }
+
+    #[test]
+    fn glob_base_stops_at_first_wildcard() {
+        assert_eq!(glob_base("icons/*.png"), Path::new("icons"));
+        assert_eq!(glob_base("assets/ui/**/*.png"), Path::new("assets/ui"));
+        assert_eq!(glob_base("assets/v[12]/logo.png"), Path::new("assets"));
+        assert_eq!(glob_base("*.png"), Path::new(""));
+    }
+
+    #[test]
+    fn relative_to_falls_back_to_file_name() {
+        let files = relative_to(
+            Path::new("assets"),
+            vec![
+                PathBuf::from("assets/ui/logo.png"),
+                PathBuf::from("other/icon.png"),
+            ],
+        );
+
+        assert_eq!(files[0].relative, Path::new("ui/logo.png"));
+        assert_eq!(files[1].relative, Path::new("icon.png"));
+    }
+
+    #[test]
+    fn output_dir_mirrors_input_tree() {
+        let file = input("assets/ui/logo.png", "ui/logo.png");
+
+        assert_eq!(
+            output_path(&file, Some(Path::new("src/images")), "c"),
+            Path::new("src/images/ui/logo.c")
+        );
+        assert_eq!(
+            output_path(&file, None, "bin"),
+            Path::new("assets/ui/logo.bin")
+        );
+    }
+
+    #[test]
+    fn glob_skips_non_png_matches() {
+        let dir = std::env::temp_dir().join(format!("png2lvgl-glob-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        for name in ["a.png", "b.PNG", "notes.txt", "sub/c.png"] {
+            fs::write(dir.join(name), b"").unwrap();
+        }
+
+        let files = expand_glob(&format!("{}/*", dir.display())).unwrap();
+        let names: Vec<&Path> = files.iter().map(|f| f.relative.as_path()).collect();
+        assert_eq!(names, [Path::new("a.png"), Path::new("b.PNG")]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
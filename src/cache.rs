@@ -0,0 +1,178 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Fabian Schmieder
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use tracing::{debug, warn};
+
+use crate::Args;
+use png2lvgl::Result;
+
+/// First line of the cache file.
+const CACHE_HEADER: &str = "# png2lvgl cache v1";
+
+/// What an output was generated from, and what it contained afterwards.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Entry {
+    key: u64,
+    outputs: u64,
+}
+
+/// Hashes of earlier conversions, so that unchanged inputs skip decoding.
+///
+/// Hashes come from the standard library's hasher, which is only stable
+/// within one build; a different build misses the cache and converts again.
+pub struct Cache {
+    path: PathBuf,
+    entries: Mutex<HashMap<PathBuf, Entry>>,
+}
+
+impl Cache {
+    /// Read the cache file at `path`; a missing file starts an empty cache.
+    pub fn load(path: &Path) -> Self {
+        let entries = fs::read_to_string(path)
+            .map(|text| parse(&text))
+            .unwrap_or_default();
+        debug!(cache = %path.display(), entries = entries.len(), "Loaded cache");
+        Self {
+            path: path.to_path_buf(),
+            entries: Mutex::new(entries),
+        }
+    }
+
+    /// Whether `outputs` were generated from `key` and are unmodified.
+    ///
+    /// The first output is the one the entry is stored under.
+    pub fn is_fresh(&self, key: u64, outputs: &[&Path]) -> bool {
+        let Some(entry) = self.lock().get(outputs[0]).copied() else {
+            return false;
+        };
+        entry.key == key && hash_files(outputs) == Some(entry.outputs)
+    }
+
+    /// Remember that `outputs` were generated from `key`.
+    pub fn record(&self, key: u64, outputs: &[&Path]) {
+        if let Some(hash) = hash_files(outputs) {
+            self.lock()
+                .insert(outputs[0].to_path_buf(), Entry { key, outputs: hash });
+        }
+    }
+
+    /// Write the cache file.
+    pub fn save(&self) -> Result<()> {
+        let entries = self.lock();
+        let mut lines: Vec<String> = entries
+            .iter()
+            .map(|(path, entry)| {
+                format!(
+                    "{:016x} {:016x} {}",
+                    entry.key,
+                    entry.outputs,
+                    path.display()
+                )
+            })
+            .collect();
+        lines.sort_unstable();
+
+        let mut text = format!("{CACHE_HEADER}\n");
+        for line in lines {
+            let _ = writeln!(text, "{line}");
+        }
+        crate::write_atomic(&self.path, text.as_bytes())?;
+        debug!(cache = %self.path.display(), entries = entries.len(), "Saved cache");
+        Ok(())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<PathBuf, Entry>> {
+        self.entries
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+/// Hash of everything that determines the output for `input`: the PNG bytes,
+/// the conversion options and the converter version.
+pub fn key(args: &Args, input: &Path, var_name: &str, output: &Path) -> Result<u64> {
+    let mut hasher = DefaultHasher::new();
+    png2lvgl::version().hash(&mut hasher);
+    fs::read(input)?.hash(&mut hasher);
+    (
+        &args.format,
+        args.lvgl_version(),
+        args.big_endian,
+        args.quantize,
+        args.dither,
+        args.chroma_key,
+        args.all_depths,
+        args.alpha_source,
+        args.stride_align,
+        args.premultiply,
+        args.compress,
+    )
+        .hash(&mut hasher);
+    (
+        args.bin,
+        args.header,
+        var_name,
+        input.file_name(),
+        output.file_name(),
+    )
+        .hash(&mut hasher);
+    Ok(hasher.finish())
+}
+
+/// Combined hash of the files' contents, `None` if one cannot be read.
+fn hash_files(paths: &[&Path]) -> Option<u64> {
+    let mut hasher = DefaultHasher::new();
+    for path in paths {
+        fs::read(path).ok()?.hash(&mut hasher);
+    }
+    Some(hasher.finish())
+}
+
+fn parse(text: &str) -> HashMap<PathBuf, Entry> {
+    text.lines()
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let entry = parse_line(line);
+            if entry.is_none() {
+                warn!("Ignoring malformed cache line: {line}");
+            }
+            entry
+        })
+        .collect()
+}
+
+/// Parse `<key> <outputs> <path>`, with both hashes in hex.
+fn parse_line(line: &str) -> Option<(PathBuf, Entry)> {
+    let mut fields = line.splitn(3, ' ');
+    let key = u64::from_str_radix(fields.next()?, 16).ok()?;
+    let outputs = u64::from_str_radix(fields.next()?, 16).ok()?;
+    Some((PathBuf::from(fields.next()?), Entry { key, outputs }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_reads_saved_lines() {
+        let entries = parse(&format!(
+            "{CACHE_HEADER}\n00000000000000ff 0000000000000010 out/my icon.c\nbroken\n"
+        ));
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(
+            entries.get(Path::new("out/my icon.c")),
+            Some(&Entry {
+                key: 0xFF,
+                outputs: 0x10
+            })
+        );
+    }
+}
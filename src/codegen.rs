@@ -1,13 +1,17 @@
 // SPDX-License-Identifier: GPL-3.0-only
 // Copyright (C) 2025 Fabian Schmieder
 
+use std::collections::HashMap;
 use std::io::Write;
 
-use image::{DynamicImage, Rgba};
-use tracing::{debug, instrument};
+use image::{DynamicImage, GenericImageView, GrayImage, Luma, Rgba, RgbaImage, imageops};
+use tracing::{debug, info, instrument, warn};
 
-use crate::error::{FormatError, Result};
-use crate::format::{ColorFormat, LvglVersion};
+use crate::compress::{self, CompressMethod};
+use crate::dither::{self, DitherMethod};
+use crate::error::{FormatError, Png2LvglError, Result};
+use crate::format::{self, AlphaSource, ColorFormat, LvglVersion};
+use crate::quantize::{self, QuantizeMethod};
 
 /// Number of bytes per line in the hex data array output.
 const HEX_BYTES_PER_LINE: usize = 16;
@@ -22,6 +26,41 @@ const RGB565_RED_SHIFT: u16 = 8;
 const RGB565_GREEN_SHIFT: u16 = 3;
 const RGB565_BLUE_SHIFT: u16 = 3;
 
+/// Representable levels per R, G, B channel (used for dithering).
+const RGB565_LEVELS: [u16; 3] = [32, 64, 32];
+const RGB332_LEVELS: [u16; 3] = [8, 8, 4];
+
+/// RGB332 bit masks and shifts.
+const RGB332_RED_MASK: u8 = 0xE0;
+const RGB332_GREEN_MASK: u8 = 0xE0;
+const RGB332_GREEN_SHIFT: u8 = 3;
+const RGB332_BLUE_SHIFT: u8 = 6;
+
+/// Levels of an 8-bit channel that is passed through unchanged.
+const FULL_LEVELS: u16 = 256;
+
+/// Pixels with alpha below this are replaced by the chroma key.
+const CHROMA_ALPHA_THRESHOLD: u8 = 0x80;
+
+/// LVGL's default `LV_COLOR_CHROMA_KEY` (pure green).
+const DEFAULT_CHROMA_KEY: [u8; 3] = [0x00, 0xFF, 0x00];
+
+/// `LV_IMAGE_FLAGS_PREMULTIPLIED`.
+const FLAG_PREMULTIPLIED: u16 = 0x0001;
+
+/// `LV_IMAGE_FLAGS_COMPRESSED`.
+const FLAG_COMPRESSED: u16 = 0x0008;
+
+/// `LV_IMAGE_HEADER_MAGIC` of LVGL 9 binary images.
+const LV_IMAGE_HEADER_MAGIC: u8 = 0x19;
+
+/// Largest width or height an LVGL 8 header can hold (11 bits).
+const V8_MAX_DIMENSION: u32 = 0x7FF;
+
+/// Bit positions of width and height in the LVGL 8 header word.
+const V8_HEADER_WIDTH_SHIFT: u32 = 10;
+const V8_HEADER_HEIGHT_SHIFT: u32 = 21;
+
 /// Bits in a byte.
 const BITS_PER_BYTE: u8 = 8;
 
@@ -36,10 +75,192 @@ pub struct GenerateParams<'a> {
     pub format: &'a ColorFormat,
     pub lvgl_version: LvglVersion,
     pub big_endian: bool,
+    pub quantize: QuantizeMethod,
+    pub dither: DitherMethod,
+    pub chroma_key: Option<[u8; 3]>,
+    pub all_depths: bool,
+    pub alpha_source: AlphaSource,
+    /// Row alignment in bytes (LVGL 9 only); 1 keeps rows tightly packed.
+    pub stride_align: usize,
+    /// Store color premultiplied by alpha (LVGL 9 only).
+    pub premultiply: bool,
+    /// Compress the image data (LVGL 9 only).
+    pub compress: CompressMethod,
     pub source_file: &'a str,
     pub output_file: &'a str,
 }
 
+/// Where the alpha channel of RGB565 + alpha formats is stored.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum AlphaLayout {
+    /// No alpha channel.
+    None,
+    /// Separate alpha plane after the color plane (LVGL 9 `RGB565A8`).
+    Planar,
+    /// Alpha byte directly after each pixel's color bytes (LVGL 8 `TRUE_COLOR_ALPHA`).
+    Interleaved,
+}
+
+/// Alpha storage of an RGB565-based format.
+const fn alpha_layout(format: &ColorFormat, lvgl_version: LvglVersion) -> AlphaLayout {
+    match (format, lvgl_version) {
+        (ColorFormat::Argb8565, _) | (ColorFormat::TrueColorAlpha, LvglVersion::V8) => {
+            AlphaLayout::Interleaved
+        }
+        (ColorFormat::TrueColorAlpha, LvglVersion::V9) => AlphaLayout::Planar,
+        _ => AlphaLayout::None,
+    }
+}
+
+/// Pixel encodings selected by `LV_COLOR_DEPTH` in LVGL 8.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ColorDepth {
+    Rgb332,
+    Rgb565,
+    Rgb565Swapped,
+    Argb8888,
+}
+
+impl ColorDepth {
+    const ALL: [Self; 4] = [
+        Self::Rgb332,
+        Self::Rgb565,
+        Self::Rgb565Swapped,
+        Self::Argb8888,
+    ];
+
+    /// Preprocessor condition selecting this encoding.
+    const fn condition(self) -> &'static str {
+        match self {
+            Self::Rgb332 => "LV_COLOR_DEPTH == 1 || LV_COLOR_DEPTH == 8",
+            Self::Rgb565 => "LV_COLOR_DEPTH == 16 && LV_COLOR_16_SWAP == 0",
+            Self::Rgb565Swapped => "LV_COLOR_DEPTH == 16 && LV_COLOR_16_SWAP != 0",
+            Self::Argb8888 => "LV_COLOR_DEPTH == 32",
+        }
+    }
+
+    const fn description(self) -> &'static str {
+        match self {
+            Self::Rgb332 => "Red: 3 bit, Green: 3 bit, Blue: 2 bit",
+            Self::Rgb565 => "Red: 5 bit, Green: 6 bit, Blue: 5 bit",
+            Self::Rgb565Swapped => {
+                "Red: 5 bit, Green: 6 bit, Blue: 5 bit BUT the 2 bytes are swapped"
+            }
+            Self::Argb8888 => "Alpha: 8 bit, Red: 8 bit, Green: 8 bit, Blue: 8 bit",
+        }
+    }
+
+    const fn levels(self) -> [u16; 3] {
+        match self {
+            Self::Rgb332 => RGB332_LEVELS,
+            Self::Rgb565 | Self::Rgb565Swapped => RGB565_LEVELS,
+            Self::Argb8888 => [FULL_LEVELS; 3],
+        }
+    }
+
+    /// Append one pixel; with `alpha`, 8/16-bit colors are followed by an alpha byte.
+    fn push_pixel(self, data: &mut Vec<u8>, pixel: Rgba<u8>, alpha: bool) {
+        let Rgba([red, green, blue, opacity]) = pixel;
+        match self {
+            Self::Rgb332 => data.push(encode_rgb332(red, green, blue)),
+            Self::Rgb565 => data.extend(encode_rgb565(red, green, blue).to_le_bytes()),
+            Self::Rgb565Swapped => data.extend(encode_rgb565(red, green, blue).to_be_bytes()),
+            Self::Argb8888 => {
+                data.extend([blue, green, red, if alpha { opacity } else { u8::MAX }]);
+                return;
+            }
+        }
+        if alpha {
+            data.push(opacity);
+        }
+    }
+}
+
+/// Pixel data for every `LV_COLOR_DEPTH` (LVGL 8 multi-depth output).
+struct DepthVariants {
+    blocks: Vec<(ColorDepth, Vec<u8>)>,
+    /// C expression for `data_size` at the selected depth.
+    size_expr: String,
+}
+
+/// Pixel data produced by one of the format encoders.
+struct EncodedImage {
+    width: u32,
+    height: u32,
+    /// Palette entries (indexed formats only), each stored as B, G, R, A.
+    palette: Vec<[u8; PALETTE_ENTRY_SIZE]>,
+    /// Pixel data blocks, written in order (e.g. color plane, then alpha plane).
+    planes: Vec<Vec<u8>>,
+    /// Per-depth pixel data, written after `planes` (LVGL 8 multi-depth only).
+    depth_variants: Option<DepthVariants>,
+    /// Additional lines for the header comment.
+    notes: Vec<String>,
+    /// LVGL compressed-image blob replacing palette and planes in the output.
+    compressed: Option<Vec<u8>>,
+}
+
+impl EncodedImage {
+    fn data_size(&self) -> usize {
+        self.compressed.as_ref().map_or_else(
+            || {
+                self.palette.len() * PALETTE_ENTRY_SIZE
+                    + self.planes.iter().map(Vec::len).sum::<usize>()
+            },
+            Vec::len,
+        )
+    }
+
+    /// Palette and planes as one contiguous block, as LVGL decompresses it.
+    fn raw_data(&self) -> Vec<u8> {
+        let mut data: Vec<u8> = self.palette.iter().flatten().copied().collect();
+        for plane in &self.planes {
+            data.extend_from_slice(plane);
+        }
+        data
+    }
+
+    /// Bytes per row of the first plane (LVGL 9 `header.stride`).
+    ///
+    /// Every encoder starts each row on a byte boundary, so this is the
+    /// plane length divided by the row count.
+    fn stride(&self) -> usize {
+        self.planes.first().map_or(0, |plane| self.row_bytes(plane))
+    }
+
+    fn row_bytes(&self, plane: &[u8]) -> usize {
+        plane.len() / self.height.max(1) as usize
+    }
+
+    /// Zero-pad the rows of every plane so the stride is a multiple of `align`.
+    ///
+    /// LVGL derives the stride of later planes from `header.stride` (the
+    /// `RGB565A8` alpha plane uses half of it), so they keep their ratio to
+    /// the first plane.
+    fn align_rows(&mut self, align: usize) {
+        let first_row = self.stride();
+        let stride = first_row.next_multiple_of(align);
+        if first_row == 0 || stride == first_row {
+            return;
+        }
+
+        let height = self.height as usize;
+        let planes = std::mem::take(&mut self.planes);
+        self.planes = planes
+            .into_iter()
+            .map(|plane| {
+                let row = self.row_bytes(&plane);
+                let padded_row = stride * row / first_row;
+                let mut padded = Vec::with_capacity(padded_row * height);
+                for chunk in plane.chunks_exact(row) {
+                    padded.extend_from_slice(chunk);
+                    padded.resize(padded.len() + padded_row - row, 0);
+                }
+                padded
+            })
+            .collect();
+    }
+}
+
 /// Generate the complete LVGL C file for the given image.
 #[instrument(skip(img, writer, params), fields(format = ?params.format, var_name = params.var_name))]
 pub fn generate<W: Write>(
@@ -48,76 +269,231 @@ pub fn generate<W: Write>(
     params: &GenerateParams<'_>,
 ) -> Result<()> {
     debug!(?params.lvgl_version, "Generating C code");
-    write_header(writer, params)?;
 
-    let format_const = params.format.lvgl_const(params.lvgl_version);
+    let encoded = encode(img, params)?;
+    write_header(writer, params, &encoded.notes)?;
+    write_image(writer, params, &encoded)?;
 
-    match params.format {
+    debug!("C code generation complete");
+    Ok(())
+}
+
+/// Generate an LVGL binary image file (`.bin`) for the given image.
+///
+/// The pixel data is identical to the C array; only the container differs.
+#[instrument(skip(img, writer, params), fields(format = ?params.format))]
+pub fn generate_bin<W: Write>(
+    img: &DynamicImage,
+    writer: &mut W,
+    params: &GenerateParams<'_>,
+) -> Result<()> {
+    debug!(?params.lvgl_version, "Generating binary image");
+
+    let encoded = encode(img, params)?;
+    if encoded.depth_variants.is_some() {
+        return Err(Png2LvglError::Config(
+            "Binary images cannot hold multiple color depths".to_string(),
+        ));
+    }
+    for note in &encoded.notes {
+        debug!(note, "Binary image note");
+    }
+
+    write_bin_header(writer, params, &encoded)?;
+    if let Some(compressed) = &encoded.compressed {
+        writer.write_all(compressed)?;
+    } else {
+        writer.write_all(&encoded.raw_data())?;
+    }
+
+    debug!("Binary image generation complete");
+    Ok(())
+}
+
+/// Run the format encoder and the LVGL 9 post-processing steps.
+fn encode(img: &DynamicImage, params: &GenerateParams<'_>) -> Result<EncodedImage> {
+    if !params.format.supports(params.lvgl_version) {
+        return Err(FormatError::UnsupportedVersion {
+            format: format!("{:?}", params.format),
+            version: params.lvgl_version.name().to_string(),
+        }
+        .into());
+    }
+
+    check_version_options(params)?;
+
+    let premultiplied;
+    let img = if premultiplies(params) {
+        premultiplied = DynamicImage::ImageRgba8(premultiply(img.to_rgba8()));
+        &premultiplied
+    } else {
+        img
+    };
+
+    let mut encoded = match params.format {
         ColorFormat::Indexed1
         | ColorFormat::Indexed2
         | ColorFormat::Indexed4
         | ColorFormat::Indexed8 => {
             let bpp = params.format.bpp().expect("indexed format must have bpp");
-            write_indexed(
-                img,
-                writer,
-                params.var_name,
-                format_const,
-                bpp,
-                params.lvgl_version,
-            )?;
+            encode_indexed(img, bpp, params.quantize, params.dither)
         }
         ColorFormat::Alpha1 | ColorFormat::Alpha2 | ColorFormat::Alpha4 | ColorFormat::Alpha8 => {
             let bpp = params.format.bpp().expect("alpha format must have bpp");
-            write_alpha(
-                img,
-                writer,
-                params.var_name,
-                format_const,
-                bpp,
-                params.lvgl_version,
-            )?;
+            encode_alpha(img, bpp, params.alpha_source, params.dither)
         }
-        ColorFormat::TrueColor | ColorFormat::TrueColorAlpha => {
-            write_true_color(
-                img,
-                writer,
-                params.var_name,
+        ColorFormat::TrueColor | ColorFormat::TrueColorAlpha | ColorFormat::Argb8565
+            if params.all_depths =>
+        {
+            encode_depth_variants(
+                &img.to_rgba8(),
                 params.format.has_alpha(),
-                format_const,
-                params.big_endian,
-                params.lvgl_version,
-            )?;
+                None,
+                params.dither,
+            )
         }
-        ColorFormat::TrueColorChroma => {
-            return Err(FormatError::NotImplemented {
-                format: "TrueColorChroma".to_string(),
+        ColorFormat::TrueColor
+        | ColorFormat::TrueColorAlpha
+        | ColorFormat::Argb8565
+        | ColorFormat::Rgb565Swapped => {
+            let swapped = matches!(params.format, ColorFormat::Rgb565Swapped);
+            let mut encoded = encode_true_color(
+                img,
+                alpha_layout(params.format, params.lvgl_version),
+                params.big_endian || swapped,
+                params.dither,
+            );
+            if swapped && matches!(params.lvgl_version, LvglVersion::V8) {
+                encoded
+                    .notes
+                    .push("Requires LV_COLOR_16_SWAP 1".to_string());
             }
-            .into());
+            encoded
+        }
+        ColorFormat::TrueColorChroma => encode_chroma(
+            img,
+            params.chroma_key,
+            params.big_endian,
+            params.dither,
+            params.lvgl_version,
+            params.all_depths,
+        ),
+        ColorFormat::Argb8888 | ColorFormat::Xrgb8888 | ColorFormat::Rgb888 => {
+            encode_bgr888(img, params.format, params.lvgl_version)
         }
+        ColorFormat::L8 | ColorFormat::Al88 => encode_luminance(img, params.format.has_alpha()),
         ColorFormat::Auto => unreachable!("Auto should be resolved before codegen"),
+    };
+
+    if params.stride_align > 1 {
+        encoded.align_rows(params.stride_align);
+        encoded.notes.push(format!(
+            "Row stride: {} bytes (aligned to {})",
+            encoded.stride(),
+            params.stride_align
+        ));
     }
 
-    debug!("C code generation complete");
-    Ok(())
+    if params.compress != CompressMethod::None {
+        compress_image(&mut encoded, params);
+    }
+
+    Ok(encoded)
+}
+
+/// Reject options that only exist for the other LVGL version.
+fn check_version_options(params: &GenerateParams<'_>) -> Result<()> {
+    if matches!(params.lvgl_version, LvglVersion::V9) {
+        if params.all_depths {
+            return Err(Png2LvglError::Config(
+                "all-depths requires LVGL 8.x".to_string(),
+            ));
+        }
+        return Ok(());
+    }
+
+    let option = if params.stride_align > 1 {
+        "Row stride alignment"
+    } else if params.premultiply {
+        "Premultiplied alpha"
+    } else if params.compress != CompressMethod::None {
+        "Compression"
+    } else {
+        return Ok(());
+    };
+    Err(Png2LvglError::Config(format!("{option} requires LVGL 9.x")))
+}
+
+/// Replace the image data by LVGL's compressed-image blob.
+fn compress_image(encoded: &mut EncodedImage, params: &GenerateParams<'_>) {
+    let raw = encoded.raw_data();
+    let compressed = compress::compress(&raw, params.compress, rle_block_size(params));
+
+    #[allow(clippy::cast_precision_loss)]
+    let ratio = compressed.len() as f64 / raw.len().max(1) as f64 * 100.0;
+    encoded.notes.push(format!(
+        "Compression: {}, {} -> {} bytes ({ratio:.1}%)",
+        params.compress.name(),
+        raw.len(),
+        compressed.len()
+    ));
+    if let Some(requirement) = params.compress.requirement() {
+        encoded.notes.push(format!("Requires {requirement}"));
+    }
+    encoded.compressed = Some(compressed);
+}
+
+/// Pixel size LVGL's RLE decoder uses for the format (whole bytes, and two
+/// for `RGB565A8`).
+const fn rle_block_size(params: &GenerateParams<'_>) -> usize {
+    match params.format {
+        ColorFormat::TrueColor
+        | ColorFormat::TrueColorAlpha
+        | ColorFormat::TrueColorChroma
+        | ColorFormat::Rgb565Swapped
+        | ColorFormat::Al88 => 2,
+        ColorFormat::Argb8565 | ColorFormat::Rgb888 => 3,
+        ColorFormat::Argb8888 | ColorFormat::Xrgb8888 => 4,
+        _ => 1,
+    }
+}
+
+/// Whether color is stored premultiplied for this format and options.
+const fn premultiplies(params: &GenerateParams<'_>) -> bool {
+    params.premultiply && params.format.can_premultiply()
+}
+
+/// Scale every color channel by its pixel's alpha.
+fn premultiply(mut rgba: RgbaImage) -> RgbaImage {
+    for pixel in rgba.pixels_mut() {
+        let alpha = u16::from(pixel[3]);
+        for channel in &mut pixel.0[..3] {
+            #[allow(clippy::cast_possible_truncation)]
+            let scaled = ((u16::from(*channel) * alpha + 127) / 255) as u8;
+            *channel = scaled;
+        }
+    }
+    rgba
 }
 
 // ---------------------------------------------------------------------------
 // Header
 // ---------------------------------------------------------------------------
 
-fn write_header<W: Write>(writer: &mut W, params: &GenerateParams<'_>) -> Result<()> {
+/// Opening lines of the file comment shared by the C and header files.
+fn write_banner<W: Write>(
+    writer: &mut W,
+    params: &GenerateParams<'_>,
+    file_name: &str,
+) -> Result<()> {
     let version = built_info::GIT_VERSION.unwrap_or(built_info::PKG_VERSION);
-    let lvgl_ver = match params.lvgl_version {
-        LvglVersion::V8 => "LVGL 8.x",
-        LvglVersion::V9 => "LVGL 9.x",
-    };
+    let lvgl_ver = params.lvgl_version.name();
 
     writeln!(writer, "/**")?;
     writeln!(
         writer,
-        " * {} - LVGL image asset of {}",
-        params.output_file, params.source_file
+        " * {file_name} - LVGL image asset of {}",
+        params.source_file
     )?;
     writeln!(writer, " * ")?;
     writeln!(
@@ -129,19 +505,10 @@ fn write_header<W: Write>(writer: &mut W, params: &GenerateParams<'_>) -> Result
     writeln!(writer, " * ")?;
     writeln!(writer, " * Target: {lvgl_ver}")?;
     writeln!(writer, " * Format: {}", params.format.description())?;
+    Ok(())
+}
 
-    if params.format.is_rgb565() {
-        let order = if params.big_endian {
-            "big-endian"
-        } else {
-            "little-endian"
-        };
-        writeln!(writer, " * RGB565 Byte Order: {order}")?;
-    }
-
-    writeln!(writer, " */")?;
-    writeln!(writer)?;
-
+fn write_lvgl_include<W: Write>(writer: &mut W) -> Result<()> {
     write!(
         writer,
         "\
@@ -159,6 +526,48 @@ fn write_header<W: Write>(writer: &mut W, params: &GenerateParams<'_>) -> Result
     #include \"lvgl/lvgl.h\"
 #endif
 
+"
+    )?;
+    Ok(())
+}
+
+fn write_header<W: Write>(
+    writer: &mut W,
+    params: &GenerateParams<'_>,
+    notes: &[String],
+) -> Result<()> {
+    write_banner(writer, params, params.output_file)?;
+
+    if matches!(params.format, ColorFormat::Rgb565Swapped) {
+        writeln!(writer, " * RGB565 Byte Order: swapped (big-endian)")?;
+    } else if params.format.is_rgb565() && !params.all_depths {
+        let order = if params.big_endian {
+            "big-endian"
+        } else {
+            "little-endian"
+        };
+        writeln!(writer, " * RGB565 Byte Order: {order}")?;
+    }
+
+    if params.dither != DitherMethod::None {
+        writeln!(writer, " * Dithering: {}", params.dither.name())?;
+    }
+
+    if premultiplies(params) {
+        writeln!(writer, " * Alpha: premultiplied")?;
+    }
+
+    for note in notes {
+        writeln!(writer, " * {note}")?;
+    }
+
+    writeln!(writer, " */")?;
+    writeln!(writer)?;
+
+    write_lvgl_include(writer)?;
+    write!(
+        writer,
+        "\
 #ifndef LV_ATTRIBUTE_MEM_ALIGN
 #define LV_ATTRIBUTE_MEM_ALIGN
 #endif
@@ -178,13 +587,15 @@ fn write_header<W: Write>(writer: &mut W, params: &GenerateParams<'_>) -> Result
 // ---------------------------------------------------------------------------
 
 /// Pack pixel values into bytes, MSB-first, row by row.
+///
+/// `pixel_value` must return values that already fit into `bpp` bits.
 fn pack_pixels_msb(
     width: u32,
     height: u32,
     bpp: u8,
     pixel_value: impl Fn(u32, u32) -> u8,
 ) -> Vec<u8> {
-    let mask = (1u8 << bpp) - 1;
+    let mask = u8::MAX >> (BITS_PER_BYTE - bpp);
     let mut data = Vec::new();
 
     for y in 0..height {
@@ -192,7 +603,7 @@ fn pack_pixels_msb(
         let mut shift = BITS_PER_BYTE - bpp;
 
         for x in 0..width {
-            let value = (pixel_value(x, y) >> (BITS_PER_BYTE - bpp)) & mask;
+            let value = pixel_value(x, y) & mask;
             byte |= value << shift;
 
             if shift == 0 {
@@ -214,70 +625,235 @@ fn pack_pixels_msb(
 }
 
 // ---------------------------------------------------------------------------
-// Format writers
+// Format encoders
 // ---------------------------------------------------------------------------
 
-#[instrument(skip(img, writer))]
-fn write_indexed<W: Write>(
+#[instrument(skip(img))]
+fn encode_indexed(
     img: &DynamicImage,
-    writer: &mut W,
-    var_name: &str,
-    format_const: &str,
     bpp: u8,
-    lvgl_version: LvglVersion,
-) -> Result<()> {
-    let gray = img.to_luma8();
-    let (width, height) = gray.dimensions();
+    method: QuantizeMethod,
+    dither_method: DitherMethod,
+) -> EncodedImage {
+    let rgba = img.to_rgba8();
+    let (width, height) = rgba.dimensions();
     let palette_size: usize = 1 << bpp;
-    debug!(width, height, bpp, "Writing indexed data");
+    debug!(width, height, bpp, "Encoding indexed data");
 
-    write_array_open(writer, var_name)?;
+    let mut notes = Vec::new();
+    let mut colors = format::unique_colors(&rgba);
+    let quantized = colors.len() > palette_size;
+    if quantized {
+        info!("Image has more than {palette_size} colors; quantizing");
+        colors = quantize::quantize(&rgba, palette_size, method);
+    }
 
-    // Palette (RGBA32)
-    for i in 0..palette_size {
-        #[allow(clippy::cast_possible_truncation)]
-        let val = (i * 255 / (palette_size - 1)) as u8;
-        writeln!(
-            writer,
-            "  0x{val:02x}, 0x{val:02x}, 0x{val:02x}, 0xff, \t/*Color of index {i}*/"
-        )?;
+    // An exact palette is lossless; dithering would only move pixels off
+    // their own color
+    let mut target = rgba.clone();
+    if quantized {
+        let mut nearest: HashMap<Rgba<u8>, u8> = HashMap::new();
+        let spread = indexed_dither_step(colors.len());
+        dither::dither(
+            &mut target,
+            width,
+            dither_method,
+            [spread, spread, spread, 0.0],
+            |value| {
+                let color = Rgba(value.map(|v| dither::quantize_level(v, FULL_LEVELS)));
+                let index = *nearest
+                    .entry(color)
+                    .or_insert_with(|| quantize::nearest(&colors, color));
+                colors[usize::from(index)].0
+            },
+        );
     }
-    writeln!(writer)?;
 
-    let data = pack_pixels_msb(width, height, bpp, |x, y| gray.get_pixel(x, y)[0]);
+    let indices = quantize::remap(&target, &colors);
+    if quantized {
+        let mse = quantize::mse(&rgba, &colors, &indices);
+        debug!(mse, "Quantization complete");
+        notes.push(format!(
+            "Quantization: {} to {} colors, MSE {mse:.2} (PSNR {:.1} dB)",
+            method.name(),
+            colors.len(),
+            quantize::psnr(mse)
+        ));
+    }
 
-    write_data_array(writer, &data)?;
-    writeln!(writer, "}};\n")?;
+    // Unused palette entries are zeroed
+    let palette = (0..palette_size)
+        .map(|i| {
+            let Rgba([red, green, blue, alpha]) = colors.get(i).copied().unwrap_or(Rgba([0; 4]));
+            [blue, green, red, alpha]
+        })
+        .collect();
 
-    let total_size = palette_size * PALETTE_ENTRY_SIZE + data.len();
-    write_descriptor(
-        writer,
-        var_name,
+    let data = pack_pixels_msb(width, height, bpp, |x, y| indices[(y * width + x) as usize]);
+
+    EncodedImage {
         width,
         height,
-        format_const,
-        total_size,
-        lvgl_version,
-    )?;
-    Ok(())
+        palette,
+        planes: vec![data],
+        depth_variants: None,
+        notes,
+        compressed: None,
+    }
 }
 
-#[instrument(skip(img, writer))]
-fn write_true_color<W: Write>(
+#[instrument(skip(img))]
+fn encode_true_color(
     img: &DynamicImage,
-    writer: &mut W,
-    var_name: &str,
-    alpha: bool,
-    format_const: &str,
+    layout: AlphaLayout,
     big_endian: bool,
+    dither_method: DitherMethod,
+) -> EncodedImage {
+    let mut rgba = img.to_rgba8();
+    debug!(?layout, big_endian, "Encoding true color data");
+
+    dither_rgb(&mut rgba, dither_method, RGB565_LEVELS);
+    encode_rgb565_image(&rgba, layout, big_endian)
+}
+
+#[instrument(skip(img))]
+fn encode_chroma(
+    img: &DynamicImage,
+    key: Option<[u8; 3]>,
+    big_endian: bool,
+    dither_method: DitherMethod,
     lvgl_version: LvglVersion,
-) -> Result<()> {
-    let rgba = img.to_rgba8();
+    all_depths: bool,
+) -> EncodedImage {
+    let mut rgba = img.to_rgba8();
+    let key = key.unwrap_or_else(|| derive_chroma_key(&rgba));
+    debug!(?key, big_endian, all_depths, "Encoding chroma-keyed data");
+
+    let mut encoded = if all_depths {
+        encode_depth_variants(&rgba, false, Some(key), dither_method)
+    } else {
+        dither_rgb(&mut rgba, dither_method, RGB565_LEVELS);
+        warn_chroma_collisions(apply_chroma_key(&mut rgba, key));
+        encode_rgb565_image(&rgba, AlphaLayout::None, big_endian)
+    };
+
+    let [key_red, key_green, key_blue] = key;
+    let key_hex = format!("#{key_red:02X}{key_green:02X}{key_blue:02X}");
+    encoded.notes.push(match lvgl_version {
+        LvglVersion::V8 => format!("Chroma key: {key_hex} (must match LV_COLOR_CHROMA_KEY)"),
+        LvglVersion::V9 => format!("Chroma key: {key_hex} (stored as plain RGB565 for LVGL 9.x)"),
+    });
+    encoded
+}
+
+/// Encode one block per LVGL 8 `LV_COLOR_DEPTH`, each dithered separately.
+///
+/// With `alpha`, pixels use the `TRUE_COLOR_ALPHA` layout of each depth.
+/// With `chroma_key`, transparent pixels are replaced after dithering.
+#[instrument(skip(rgba))]
+fn encode_depth_variants(
+    rgba: &RgbaImage,
+    alpha: bool,
+    chroma_key: Option<[u8; 3]>,
+    dither_method: DitherMethod,
+) -> EncodedImage {
     let (width, height) = rgba.dimensions();
-    debug!(width, height, alpha, big_endian, "Writing true color data");
+    debug!(width, height, "Encoding all LVGL 8 color depths");
+
+    let mut blocks = Vec::with_capacity(ColorDepth::ALL.len());
+    for depth in ColorDepth::ALL {
+        let mut variant = rgba.clone();
+        dither_rgb(&mut variant, dither_method, depth.levels());
+
+        if let Some(key) = chroma_key {
+            let collisions = apply_chroma_key(&mut variant, key);
+            if depth == ColorDepth::Rgb565 {
+                warn_chroma_collisions(collisions);
+            }
+        }
+
+        let mut data = Vec::new();
+        for pixel in variant.pixels() {
+            depth.push_pixel(&mut data, *pixel, alpha);
+        }
+        blocks.push((depth, data));
+    }
 
-    write_array_open(writer, var_name)?;
+    let pixel_size = if alpha {
+        "LV_IMG_PX_SIZE_ALPHA_BYTE"
+    } else {
+        "LV_COLOR_SIZE / 8"
+    };
+
+    EncodedImage {
+        width,
+        height,
+        palette: Vec::new(),
+        planes: Vec::new(),
+        depth_variants: Some(DepthVariants {
+            blocks,
+            size_expr: format!("{} * {pixel_size}", u64::from(width) * u64::from(height)),
+        }),
+        notes: vec!["Color depth: selected by LV_COLOR_DEPTH (8, 16, 16 swapped, 32)".to_string()],
+        compressed: None,
+    }
+}
+
+/// Replace transparent pixels with the chroma key.
+///
+/// Returns how many opaque pixels collide with the key after RGB565 rounding.
+fn apply_chroma_key(rgba: &mut RgbaImage, key: [u8; 3]) -> usize {
+    let [key_red, key_green, key_blue] = key;
+    let key_rgb565 = encode_rgb565(key_red, key_green, key_blue);
+
+    let mut collisions = 0;
+    for pixel in rgba.pixels_mut() {
+        if pixel[3] < CHROMA_ALPHA_THRESHOLD {
+            *pixel = Rgba([key_red, key_green, key_blue, u8::MAX]);
+        } else if encode_rgb565(pixel[0], pixel[1], pixel[2]) == key_rgb565 {
+            collisions += 1;
+        }
+    }
+    collisions
+}
+
+fn warn_chroma_collisions(collisions: usize) {
+    if collisions > 0 {
+        warn!(
+            collisions,
+            "Opaque pixels match the chroma key after RGB565 rounding and will be transparent"
+        );
+    }
+}
 
+/// Dither RGB channels to the given levels per channel; alpha is left untouched.
+fn dither_rgb(rgba: &mut RgbaImage, dither_method: DitherMethod, levels: [u16; 3]) {
+    let width = rgba.width();
+    let [red_levels, green_levels, blue_levels] = levels;
+    dither::dither(
+        rgba,
+        width,
+        dither_method,
+        [
+            dither::level_step(red_levels),
+            dither::level_step(green_levels),
+            dither::level_step(blue_levels),
+            0.0,
+        ],
+        |[red, green, blue, alpha]| {
+            [
+                dither::quantize_level(red, red_levels),
+                dither::quantize_level(green, green_levels),
+                dither::quantize_level(blue, blue_levels),
+                dither::quantize_level(alpha, FULL_LEVELS),
+            ]
+        },
+    );
+}
+
+/// Encode RGB565 pixel data with alpha stored according to `layout`.
+fn encode_rgb565_image(rgba: &RgbaImage, layout: AlphaLayout, big_endian: bool) -> EncodedImage {
+    let (width, height) = rgba.dimensions();
     let mut rgb_data = Vec::new();
     let mut alpha_data = Vec::new();
 
@@ -298,63 +874,237 @@ fn write_true_color<W: Write>(
             rgb_data.push(hi);
         }
 
-        if alpha {
-            alpha_data.push(channels[3]);
+        match layout {
+            AlphaLayout::None => {}
+            AlphaLayout::Planar => alpha_data.push(channels[3]),
+            AlphaLayout::Interleaved => rgb_data.push(channels[3]),
         }
     }
 
-    write_data_array(writer, &rgb_data)?;
-    if alpha {
-        writeln!(writer)?;
-        write_data_array(writer, &alpha_data)?;
+    let mut planes = vec![rgb_data];
+    if layout == AlphaLayout::Planar {
+        planes.push(alpha_data);
     }
-    writeln!(writer, "}};\n")?;
 
-    write_descriptor(
-        writer,
-        var_name,
+    EncodedImage {
         width,
         height,
-        format_const,
-        rgb_data.len() + alpha_data.len(),
-        lvgl_version,
-    )?;
-    Ok(())
+        palette: Vec::new(),
+        planes,
+        depth_variants: None,
+        notes: Vec::new(),
+        compressed: None,
+    }
 }
 
-#[instrument(skip(img, writer))]
-fn write_alpha<W: Write>(
+/// Pick the chroma key from the most common color of fully transparent pixels.
+///
+/// Falls back to LVGL's default `LV_COLOR_CHROMA_KEY` (pure green) when the
+/// image has no fully transparent pixels or that color is also used opaquely.
+fn derive_chroma_key(rgba: &RgbaImage) -> [u8; 3] {
+    let mut counts: HashMap<[u8; 3], usize> = HashMap::new();
+    for pixel in rgba.pixels().filter(|p| p[3] == 0) {
+        *counts.entry([pixel[0], pixel[1], pixel[2]]).or_default() += 1;
+    }
+
+    let Some(([red, green, blue], _)) = counts
+        .into_iter()
+        .max_by_key(|&(color, count)| (count, std::cmp::Reverse(color)))
+    else {
+        return DEFAULT_CHROMA_KEY;
+    };
+
+    let key_rgb565 = encode_rgb565(red, green, blue);
+    let used_opaquely = rgba
+        .pixels()
+        .any(|p| p[3] >= CHROMA_ALPHA_THRESHOLD && encode_rgb565(p[0], p[1], p[2]) == key_rgb565);
+
+    if used_opaquely {
+        DEFAULT_CHROMA_KEY
+    } else {
+        [red, green, blue]
+    }
+}
+
+/// Encode 8-bit channels in LVGL's B, G, R order, followed by alpha
+/// (`ARGB8888`) or a 0xFF padding byte (`XRGB8888`).
+#[instrument(skip(img))]
+fn encode_bgr888(
     img: &DynamicImage,
-    writer: &mut W,
-    var_name: &str,
-    format_const: &str,
-    bpp: u8,
+    format: &ColorFormat,
     lvgl_version: LvglVersion,
-) -> Result<()> {
-    let gray = img.to_luma8();
+) -> EncodedImage {
+    let rgba = img.to_rgba8();
+    let (width, height) = rgba.dimensions();
+    debug!(width, height, "Encoding 8-bit per channel data");
+
+    let mut data = Vec::new();
+    for &Rgba([red, green, blue, alpha]) in rgba.pixels() {
+        data.extend([blue, green, red]);
+        match format {
+            ColorFormat::Argb8888 => data.push(alpha),
+            ColorFormat::Xrgb8888 => data.push(u8::MAX),
+            _ => {}
+        }
+    }
+
+    let mut notes = Vec::new();
+    if matches!(lvgl_version, LvglVersion::V8) {
+        notes.push("Requires LV_COLOR_DEPTH 32".to_string());
+    }
+
+    EncodedImage {
+        width,
+        height,
+        palette: Vec::new(),
+        planes: vec![data],
+        depth_variants: None,
+        notes,
+        compressed: None,
+    }
+}
+
+/// Encode 8-bit luminance, optionally followed by alpha per pixel (`AL88`).
+#[instrument(skip(img))]
+fn encode_luminance(img: &DynamicImage, alpha: bool) -> EncodedImage {
+    let luma = img.to_luma_alpha8();
+    let (width, height) = luma.dimensions();
+    debug!(width, height, alpha, "Encoding luminance data");
+
+    let data = if alpha {
+        luma.into_raw()
+    } else {
+        luma.pixels().map(|p| p[0]).collect()
+    };
+
+    EncodedImage {
+        width,
+        height,
+        palette: Vec::new(),
+        planes: vec![data],
+        depth_variants: None,
+        notes: Vec::new(),
+        compressed: None,
+    }
+}
+
+#[instrument(skip(img))]
+fn encode_alpha(
+    img: &DynamicImage,
+    bpp: u8,
+    source: AlphaSource,
+    dither_method: DitherMethod,
+) -> EncodedImage {
+    let source = source.resolve(img);
+    let mut gray = alpha_plane(img, source);
     let (width, height) = gray.dimensions();
-    debug!(width, height, bpp, "Writing alpha data");
+    debug!(width, height, bpp, ?source, "Encoding alpha data");
 
-    write_array_open(writer, var_name)?;
+    let levels = 1u16 << bpp;
+    dither::dither(
+        &mut gray,
+        width,
+        dither_method,
+        [dither::level_step(levels)],
+        |[value]| [dither::quantize_level(value, levels)],
+    );
 
     let data = if bpp == BITS_PER_BYTE {
         gray.pixels().map(|p| p[0]).collect()
     } else {
-        pack_pixels_msb(width, height, bpp, |x, y| gray.get_pixel(x, y)[0])
+        pack_pixels_msb(width, height, bpp, |x, y| {
+            gray.get_pixel(x, y)[0] >> (BITS_PER_BYTE - bpp)
+        })
     };
 
-    write_data_array(writer, &data)?;
-    writeln!(writer, "}};\n")?;
-
-    write_descriptor(
-        writer,
-        var_name,
+    EncodedImage {
         width,
         height,
-        format_const,
-        data.len(),
-        lvgl_version,
-    )?;
+        palette: Vec::new(),
+        planes: vec![data],
+        depth_variants: None,
+        notes: vec![format!("Alpha source: {}", source.name())],
+        compressed: None,
+    }
+}
+
+/// Single-channel coverage image taken from `source`.
+fn alpha_plane(img: &DynamicImage, source: AlphaSource) -> GrayImage {
+    let channel = match source {
+        AlphaSource::Auto | AlphaSource::Luminance => return img.to_luma8(),
+        AlphaSource::InvertedLuminance => {
+            let mut gray = img.to_luma8();
+            imageops::invert(&mut gray);
+            return gray;
+        }
+        AlphaSource::Red => 0,
+        AlphaSource::Green => 1,
+        AlphaSource::Blue => 2,
+        AlphaSource::Alpha => 3,
+    };
+
+    let rgba = img.to_rgba8();
+    GrayImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        Luma([rgba.get_pixel(x, y)[channel]])
+    })
+}
+
+// ---------------------------------------------------------------------------
+// C output
+// ---------------------------------------------------------------------------
+
+fn write_image<W: Write>(
+    writer: &mut W,
+    params: &GenerateParams<'_>,
+    encoded: &EncodedImage,
+) -> Result<()> {
+    write_array_open(writer, params.var_name)?;
+
+    if let Some(compressed) = &encoded.compressed {
+        write_data_array(writer, compressed)?;
+    } else {
+        write_plain_data(writer, encoded)?;
+    }
+    writeln!(writer, "}};\n")?;
+
+    let data_size = encoded.depth_variants.as_ref().map_or_else(
+        || encoded.data_size().to_string(),
+        |variants| variants.size_expr.clone(),
+    );
+
+    write_descriptor(writer, params, encoded, &data_size)
+}
+
+/// Palette, planes and depth variants of an uncompressed image.
+fn write_plain_data<W: Write>(writer: &mut W, encoded: &EncodedImage) -> Result<()> {
+    if !encoded.palette.is_empty() {
+        for (i, [blue, green, red, alpha]) in encoded.palette.iter().enumerate() {
+            writeln!(
+                writer,
+                "  0x{blue:02x}, 0x{green:02x}, 0x{red:02x}, 0x{alpha:02x}, \t/*Color of index {i}*/"
+            )?;
+        }
+        writeln!(writer)?;
+    }
+
+    for (i, plane) in encoded.planes.iter().enumerate() {
+        if i > 0 {
+            writeln!(writer)?;
+        }
+        write_data_array(writer, plane)?;
+    }
+
+    if let Some(variants) = &encoded.depth_variants {
+        for (i, (depth, data)) in variants.blocks.iter().enumerate() {
+            if i > 0 {
+                writeln!(writer)?;
+            }
+            writeln!(writer, "#if {}", depth.condition())?;
+            writeln!(writer, "  /*Pixel format: {}*/", depth.description())?;
+            write_data_array(writer, data)?;
+            writeln!(writer, "#endif")?;
+        }
+    }
     Ok(())
 }
 
@@ -362,6 +1112,19 @@ fn write_alpha<W: Write>(
 // Helpers
 // ---------------------------------------------------------------------------
 
+/// Approximate per-channel distance between palette colors, for ordered dithering.
+#[allow(clippy::cast_precision_loss)]
+fn indexed_dither_step(palette_size: usize) -> f32 {
+    255.0 / (palette_size.max(2) as f32).cbrt()
+}
+
+/// Encode an RGB pixel to RGB332.
+const fn encode_rgb332(red: u8, green: u8, blue: u8) -> u8 {
+    (red & RGB332_RED_MASK)
+        | ((green & RGB332_GREEN_MASK) >> RGB332_GREEN_SHIFT)
+        | (blue >> RGB332_BLUE_SHIFT)
+}
+
 /// Encode an RGB pixel to RGB565.
 const fn encode_rgb565(red: u8, green: u8, blue: u8) -> u16 {
     ((red as u16 & RGB565_RED_MASK) << RGB565_RED_SHIFT)
@@ -399,25 +1162,310 @@ fn write_data_array<W: Write>(writer: &mut W, data: &[u8]) -> Result<()> {
 
 fn write_descriptor<W: Write>(
     writer: &mut W,
-    var_name: &str,
-    width: u32,
-    height: u32,
-    cf: &str,
-    size: usize,
-    lvgl_version: LvglVersion,
+    params: &GenerateParams<'_>,
+    encoded: &EncodedImage,
+    size: &str,
 ) -> Result<()> {
-    writeln!(writer, "const lv_img_dsc_t {var_name} = {{")?;
-    writeln!(writer, "  .header.cf = {cf},")?;
+    let var_name = params.var_name;
+    let cf = params.format.lvgl_const(params.lvgl_version);
 
-    if matches!(lvgl_version, LvglVersion::V8) {
-        writeln!(writer, "  .header.always_zero = 0,")?;
-        writeln!(writer, "  .header.reserved = 0,")?;
+    match params.lvgl_version {
+        LvglVersion::V8 => {
+            writeln!(writer, "const {} {var_name} = {{", descriptor_type(params))?;
+            writeln!(writer, "  .header.cf = {cf},")?;
+            writeln!(writer, "  .header.always_zero = 0,")?;
+            writeln!(writer, "  .header.reserved = 0,")?;
+            writeln!(writer, "  .header.w = {},", encoded.width)?;
+            writeln!(writer, "  .header.h = {},", encoded.height)?;
+        }
+        LvglVersion::V9 => {
+            writeln!(writer, "const {} {var_name} = {{", descriptor_type(params))?;
+            writeln!(writer, "  .header.magic = LV_IMAGE_HEADER_MAGIC,")?;
+            writeln!(writer, "  .header.cf = {cf},")?;
+            writeln!(
+                writer,
+                "  .header.flags = {},",
+                flags_expr(image_flags(params))
+            )?;
+            writeln!(writer, "  .header.w = {},", encoded.width)?;
+            writeln!(writer, "  .header.h = {},", encoded.height)?;
+            writeln!(writer, "  .header.stride = {},", encoded.stride())?;
+        }
     }
 
-    writeln!(writer, "  .header.w = {width},")?;
-    writeln!(writer, "  .header.h = {height},")?;
     writeln!(writer, "  .data_size = {size},")?;
     writeln!(writer, "  .data = {var_name}_map,")?;
     writeln!(writer, "}};")?;
     Ok(())
 }
+
+/// C type of the image descriptor.
+const fn descriptor_type(params: &GenerateParams<'_>) -> &'static str {
+    match params.lvgl_version {
+        LvglVersion::V8 => "lv_img_dsc_t",
+        LvglVersion::V9 => "lv_image_dsc_t",
+    }
+}
+
+/// LVGL 9 `header.flags` bits.
+fn image_flags(params: &GenerateParams<'_>) -> u16 {
+    let mut flags = 0;
+    if premultiplies(params) {
+        flags |= FLAG_PREMULTIPLIED;
+    }
+    if params.compress != CompressMethod::None {
+        flags |= FLAG_COMPRESSED;
+    }
+    flags
+}
+
+/// C expression for LVGL 9 `header.flags`.
+fn flags_expr(flags: u16) -> String {
+    let names: Vec<&str> = [
+        (FLAG_PREMULTIPLIED, "LV_IMAGE_FLAGS_PREMULTIPLIED"),
+        (FLAG_COMPRESSED, "LV_IMAGE_FLAGS_COMPRESSED"),
+    ]
+    .into_iter()
+    .filter(|&(bit, _)| flags & bit != 0)
+    .map(|(_, name)| name)
+    .collect();
+
+    if names.is_empty() {
+        "0".to_string()
+    } else {
+        names.join(" | ")
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Companion header
+// ---------------------------------------------------------------------------
+
+/// Generate a C header declaring the image descriptor written by [`generate`].
+///
+/// `file_name` is the header's own name, shown in the file comment.
+pub fn generate_header<W: Write>(
+    img: &DynamicImage,
+    writer: &mut W,
+    params: &GenerateParams<'_>,
+    file_name: &str,
+) -> Result<()> {
+    let (width, height) = img.dimensions();
+    let var_name = params.var_name;
+    let upper = var_name.to_uppercase();
+    let guard = format!("PNG2LVGL_{upper}_H");
+    debug!(guard, "Generating companion header");
+
+    write_banner(writer, params, file_name)?;
+    writeln!(writer, " */")?;
+    writeln!(writer)?;
+    writeln!(writer, "#ifndef {guard}")?;
+    writeln!(writer, "#define {guard}")?;
+    writeln!(writer)?;
+    writeln!(writer, "#ifdef __cplusplus")?;
+    writeln!(writer, "extern \"C\" {{")?;
+    writeln!(writer, "#endif")?;
+    writeln!(writer)?;
+    write_lvgl_include(writer)?;
+    writeln!(writer, "#define {upper}_WIDTH {width}")?;
+    writeln!(writer, "#define {upper}_HEIGHT {height}")?;
+    writeln!(
+        writer,
+        "#define {upper}_CF {}",
+        params.format.lvgl_const(params.lvgl_version)
+    )?;
+    writeln!(writer)?;
+    writeln!(
+        writer,
+        "extern const {} {var_name};",
+        descriptor_type(params)
+    )?;
+    writeln!(writer)?;
+    writeln!(writer, "#ifdef __cplusplus")?;
+    writeln!(writer, "}} /*extern \"C\"*/")?;
+    writeln!(writer, "#endif")?;
+    writeln!(writer)?;
+    writeln!(writer, "#endif /*{guard}*/")?;
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// Binary output
+// ---------------------------------------------------------------------------
+
+/// Write the `lv_img_header_t` (LVGL 8) or `lv_image_header_t` (LVGL 9)
+/// that precedes the data in a binary image file.
+fn write_bin_header<W: Write>(
+    writer: &mut W,
+    params: &GenerateParams<'_>,
+    encoded: &EncodedImage,
+) -> Result<()> {
+    let cf = params.format.lvgl_code(params.lvgl_version);
+
+    match params.lvgl_version {
+        LvglVersion::V8 => {
+            if encoded.width > V8_MAX_DIMENSION || encoded.height > V8_MAX_DIMENSION {
+                return Err(Png2LvglError::Config(format!(
+                    "LVGL 8.x binary images are limited to {V8_MAX_DIMENSION}x{V8_MAX_DIMENSION} pixels"
+                )));
+            }
+            // cf:5, always_zero:3, reserved:2, w:11, h:11
+            let header = u32::from(cf)
+                | (encoded.width << V8_HEADER_WIDTH_SHIFT)
+                | (encoded.height << V8_HEADER_HEIGHT_SHIFT);
+            writer.write_all(&header.to_le_bytes())?;
+        }
+        LvglVersion::V9 => {
+            let dimension = |value: usize| {
+                u16::try_from(value).map_err(|_| {
+                    Png2LvglError::Config(format!("{value} exceeds the LVGL 9.x header range"))
+                })
+            };
+            writer.write_all(&[LV_IMAGE_HEADER_MAGIC, cf])?;
+            writer.write_all(&image_flags(params).to_le_bytes())?;
+            for value in [
+                encoded.width as usize,
+                encoded.height as usize,
+                encoded.stride(),
+                0,
+            ] {
+                writer.write_all(&dimension(value)?.to_le_bytes())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Two pixels: opaque red and half-transparent blue.
+    fn two_pixel_image() -> DynamicImage {
+        let mut rgba = RgbaImage::new(2, 1);
+        rgba.put_pixel(0, 0, Rgba([0xFF, 0x00, 0x00, 0xFF]));
+        rgba.put_pixel(1, 0, Rgba([0x00, 0x00, 0xFF, 0x80]));
+        DynamicImage::ImageRgba8(rgba)
+    }
+
+    fn encode(format: &ColorFormat, lvgl_version: LvglVersion, big_endian: bool) -> EncodedImage {
+        encode_true_color(
+            &two_pixel_image(),
+            alpha_layout(format, lvgl_version),
+            big_endian,
+            DitherMethod::None,
+        )
+    }
+
+    #[test]
+    fn true_color_alpha_v9_is_planar() {
+        let encoded = encode(&ColorFormat::TrueColorAlpha, LvglVersion::V9, false);
+
+        assert_eq!(
+            encoded.planes,
+            vec![vec![0x00, 0xF8, 0x1F, 0x00], vec![0xFF, 0x80]]
+        );
+        assert_eq!(encoded.data_size(), 6);
+    }
+
+    #[test]
+    fn true_color_alpha_v8_is_interleaved() {
+        let encoded = encode(&ColorFormat::TrueColorAlpha, LvglVersion::V8, false);
+
+        assert_eq!(
+            encoded.planes,
+            vec![vec![0x00, 0xF8, 0xFF, 0x1F, 0x00, 0x80]]
+        );
+        assert_eq!(encoded.data_size(), 6);
+    }
+
+    #[test]
+    fn true_color_alpha_v8_interleaved_big_endian() {
+        let encoded = encode(&ColorFormat::TrueColorAlpha, LvglVersion::V8, true);
+
+        assert_eq!(
+            encoded.planes,
+            vec![vec![0xF8, 0x00, 0xFF, 0x00, 0x1F, 0x80]]
+        );
+    }
+
+    #[test]
+    fn argb8565_is_interleaved_for_both_versions() {
+        for version in [LvglVersion::V8, LvglVersion::V9] {
+            let encoded = encode(&ColorFormat::Argb8565, version, false);
+            assert_eq!(
+                encoded.planes,
+                vec![vec![0x00, 0xF8, 0xFF, 0x1F, 0x00, 0x80]]
+            );
+        }
+    }
+
+    #[test]
+    fn stride_follows_first_plane() {
+        let encoded = encode(&ColorFormat::TrueColorAlpha, LvglVersion::V9, false);
+        assert_eq!(encoded.stride(), 4);
+
+        let encoded = encode(&ColorFormat::Argb8565, LvglVersion::V9, false);
+        assert_eq!(encoded.stride(), 6);
+    }
+
+    #[test]
+    fn packed_rows_start_on_byte_boundary() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(5, 3, Rgba([0, 0, 0, 0xFF])));
+        let encoded = encode_alpha(&img, 1, AlphaSource::Alpha, DitherMethod::None);
+
+        assert_eq!(encoded.planes, vec![vec![0xF8; 3]]);
+        assert_eq!(encoded.stride(), 1);
+    }
+
+    #[test]
+    fn indexed8_keeps_full_byte_indices() {
+        let mut rgba = RgbaImage::new(3, 1);
+        rgba.put_pixel(0, 0, Rgba([0x00, 0x00, 0x00, 0xFF]));
+        rgba.put_pixel(1, 0, Rgba([0x80, 0x80, 0x80, 0xFF]));
+        rgba.put_pixel(2, 0, Rgba([0xFF, 0xFF, 0xFF, 0xFF]));
+        let encoded = encode_indexed(
+            &DynamicImage::ImageRgba8(rgba),
+            8,
+            QuantizeMethod::default(),
+            DitherMethod::None,
+        );
+
+        assert_eq!(encoded.palette.len(), 256);
+        assert_eq!(encoded.planes, vec![vec![0, 1, 2]]);
+    }
+
+    #[test]
+    fn exact_palette_is_not_dithered() {
+        let rgba = RgbaImage::from_fn(8, 8, |x, y| {
+            let level = if (x + y) % 3 == 0 { 0x40 } else { 0xC0 };
+            Rgba([level, level, 0x80, 0xFF])
+        });
+        let img = DynamicImage::ImageRgba8(rgba);
+        let plain = encode_indexed(&img, 2, QuantizeMethod::default(), DitherMethod::None);
+
+        for method in [
+            DitherMethod::FloydSteinberg,
+            DitherMethod::Atkinson,
+            DitherMethod::Bayer4,
+            DitherMethod::Bayer8,
+            DitherMethod::BlueNoise,
+        ] {
+            let dithered = encode_indexed(&img, 2, QuantizeMethod::default(), method);
+            assert_eq!(dithered.planes, plain.planes, "{}", method.name());
+            assert_eq!(dithered.palette, plain.palette, "{}", method.name());
+        }
+    }
+
+    #[test]
+    fn aligned_alpha_plane_uses_half_stride() {
+        let mut encoded = encode(&ColorFormat::TrueColorAlpha, LvglVersion::V9, false);
+        encoded.align_rows(16);
+
+        assert_eq!(encoded.stride(), 16);
+        assert_eq!(encoded.planes[0][..4], [0x00, 0xF8, 0x1F, 0x00]);
+        assert_eq!(encoded.planes[1][..2], [0xFF, 0x80]);
+        assert_eq!(encoded.planes[1].len(), 8);
+        assert_eq!(encoded.data_size(), 24);
+    }
+}
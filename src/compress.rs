@@ -0,0 +1,202 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Fabian Schmieder
+
+use tracing::debug;
+
+/// Size of LVGL's compressed-image header (method, compressed and
+/// decompressed size, each a little-endian `u32`).
+const HEADER_SIZE: usize = 12;
+
+/// Runs shorter than this are stored as literals.
+const RLE_MIN_RUN: usize = 16;
+
+/// Maximum block count in one RLE packet (7-bit counter).
+const RLE_MAX_COUNT: usize = 0x7F;
+
+/// Control byte flag marking an RLE literal packet.
+const RLE_LITERAL: u8 = 0x80;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, clap::ValueEnum)]
+pub enum CompressMethod {
+    #[default]
+    None,
+    Rle,
+    Lz4,
+}
+
+impl CompressMethod {
+    /// Name used in the C file header comment.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Rle => "RLE",
+            Self::Lz4 => "LZ4",
+        }
+    }
+
+    /// Value of LVGL's `lv_image_compress_t`.
+    const fn lvgl_value(self) -> u32 {
+        match self {
+            Self::None => 0,
+            Self::Rle => 1,
+            Self::Lz4 => 2,
+        }
+    }
+
+    /// LVGL config option the decoder needs.
+    #[must_use]
+    pub const fn requirement(self) -> Option<&'static str> {
+        match self {
+            Self::None => None,
+            Self::Rle => Some("LV_USE_RLE 1"),
+            Self::Lz4 => Some("LV_USE_LZ4_INTERNAL 1 or LV_USE_LZ4_EXTERNAL 1"),
+        }
+    }
+}
+
+/// Compress `data` and prepend LVGL's `lv_image_compressed_t` header.
+///
+/// `block_size` is the pixel size in bytes LVGL's RLE decoder uses for the
+/// color format; it is ignored by LZ4.
+pub fn compress(data: &[u8], method: CompressMethod, block_size: usize) -> Vec<u8> {
+    let payload = match method {
+        CompressMethod::None => data.to_vec(),
+        CompressMethod::Rle => rle(data, block_size.max(1)),
+        CompressMethod::Lz4 => lz4_flex::block::compress(data),
+    };
+    debug!(
+        ?method,
+        decompressed = data.len(),
+        compressed = payload.len(),
+        "Compressed image data"
+    );
+
+    let mut out = Vec::with_capacity(HEADER_SIZE + payload.len());
+    for value in [
+        method.lvgl_value(),
+        size_u32(payload.len()),
+        size_u32(data.len()),
+    ] {
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+    out.extend_from_slice(&payload);
+    out
+}
+
+fn size_u32(len: usize) -> u32 {
+    u32::try_from(len).expect("image data fits into u32")
+}
+
+/// Encode with LVGL's RLE scheme (`lv_rle_decompress`).
+///
+/// A control byte below 0x80 repeats the following block that many times;
+/// 0x80 | n copies the next n blocks verbatim. A trailing partial block is
+/// zero-padded, which the decoder truncates to the decompressed size.
+fn rle(data: &[u8], block_size: usize) -> Vec<u8> {
+    let mut padded = data.to_vec();
+    padded.resize(data.len().next_multiple_of(block_size), 0);
+    let blocks: Vec<&[u8]> = padded.chunks_exact(block_size).collect();
+
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < blocks.len() {
+        let run = run_length(&blocks[i..]);
+        if run >= RLE_MIN_RUN {
+            out.push(packet_count(run, 0));
+            out.extend_from_slice(blocks[i]);
+            i += run;
+            continue;
+        }
+
+        // Collect literals until the next run worth encoding
+        let mut count = 0;
+        while i + count < blocks.len()
+            && count < RLE_MAX_COUNT
+            && run_length(&blocks[i + count..]) < RLE_MIN_RUN
+        {
+            count += 1;
+        }
+        out.push(packet_count(count, RLE_LITERAL));
+        for block in &blocks[i..i + count] {
+            out.extend_from_slice(block);
+        }
+        i += count;
+    }
+    out
+}
+
+/// Number of leading blocks equal to the first one, capped at one packet.
+fn run_length(blocks: &[&[u8]]) -> usize {
+    blocks
+        .iter()
+        .take(RLE_MAX_COUNT)
+        .take_while(|block| **block == blocks[0])
+        .count()
+}
+
+fn packet_count(count: usize, flag: u8) -> u8 {
+    u8::try_from(count).expect("RLE packet count fits into 7 bits") | flag
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reference decoder following `lv_rle_decompress`.
+    fn rle_decode(input: &[u8], block_size: usize, len: usize) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut i = 0;
+        while i < input.len() {
+            let ctrl = input[i];
+            i += 1;
+            if ctrl & RLE_LITERAL == 0 {
+                for _ in 0..ctrl {
+                    out.extend_from_slice(&input[i..i + block_size]);
+                }
+                i += block_size;
+            } else {
+                let bytes = usize::from(ctrl & !RLE_LITERAL) * block_size;
+                out.extend_from_slice(&input[i..i + bytes]);
+                i += bytes;
+            }
+        }
+        out.truncate(len);
+        out
+    }
+
+    #[test]
+    fn rle_round_trips_runs_and_literals() {
+        let mut data = [0xAB, 0xCD].repeat(200);
+        data.extend((0..=255).map(|i: u8| i.wrapping_mul(7)));
+        data.push(0x42);
+
+        for block_size in [1, 2, 3, 4] {
+            let encoded = rle(&data, block_size);
+            assert_eq!(rle_decode(&encoded, block_size, data.len()), data);
+        }
+    }
+
+    #[test]
+    fn header_records_method_and_sizes() {
+        let data = vec![0u8; 64];
+        let out = compress(&data, CompressMethod::Rle, 2);
+
+        assert_eq!(out[..4], 1u32.to_le_bytes());
+        assert_eq!(out[4..8], 3u32.to_le_bytes());
+        assert_eq!(out[8..12], 64u32.to_le_bytes());
+        assert_eq!(out[12..], [32, 0, 0]);
+    }
+
+    #[test]
+    fn lz4_round_trips() {
+        let data = b"lvgl image data ".repeat(64);
+        let out = compress(&data, CompressMethod::Lz4, 1);
+
+        let payload = &out[HEADER_SIZE..];
+        assert_eq!(
+            lz4_flex::block::decompress(payload, data.len()).unwrap(),
+            data
+        );
+    }
+}
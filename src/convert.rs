@@ -0,0 +1,258 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Fabian Schmieder
+
+use std::io;
+use std::path::Path;
+
+use image::{DynamicImage, GenericImageView};
+
+use crate::codegen::{self, GenerateParams};
+use crate::compress::CompressMethod;
+use crate::dither::DitherMethod;
+use crate::error::{Png2LvglError, Result};
+use crate::format::{self, AlphaSource, ColorFormat, LvglVersion};
+use crate::identifier;
+use crate::quantize::QuantizeMethod;
+use crate::validation;
+
+/// Everything that determines the generated output.
+///
+/// Start from [`ConvertOptions::new`] and change the fields you need; new
+/// options may be added in later versions.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct ConvertOptions {
+    /// C variable name of the image descriptor.
+    pub var_name: String,
+    /// Color format; [`ColorFormat::Auto`] picks one from the image.
+    pub format: ColorFormat,
+    /// LVGL release whose API and binary layout the output targets.
+    pub lvgl_version: LvglVersion,
+    /// Write RGB565 big-endian.
+    pub big_endian: bool,
+    /// Palette algorithm for indexed formats with too many colors.
+    pub quantize: QuantizeMethod,
+    /// Dithering applied before reducing color or alpha depth.
+    pub dither: DitherMethod,
+    /// Chroma key color for `TrueColorChroma` (derived from transparent
+    /// pixels if `None`).
+    pub chroma_key: Option<[u8; 3]>,
+    /// Emit every `LV_COLOR_DEPTH` variant (LVGL 8 only).
+    pub all_depths: bool,
+    /// Channel that alpha-only formats read coverage from.
+    pub alpha_source: AlphaSource,
+    /// Row alignment in bytes (LVGL 9 only); 1 keeps rows tightly packed.
+    pub stride_align: usize,
+    /// Store color premultiplied by alpha (LVGL 9 only).
+    pub premultiply: bool,
+    /// Compress the image data (LVGL 9 only).
+    pub compress: CompressMethod,
+    /// Input file name mentioned in the generated file's header comment.
+    pub source_file: String,
+    /// Output file name mentioned in the generated file's header comment.
+    pub output_file: String,
+}
+
+impl Default for ConvertOptions {
+    fn default() -> Self {
+        Self::new("image")
+    }
+}
+
+impl ConvertOptions {
+    /// Options with the command line's defaults and the given variable name.
+    #[must_use]
+    pub fn new(var_name: impl Into<String>) -> Self {
+        let var_name = var_name.into();
+        Self {
+            source_file: format!("{var_name}.png"),
+            output_file: format!("{var_name}.c"),
+            var_name,
+            format: ColorFormat::Auto,
+            lvgl_version: LvglVersion::V9,
+            big_endian: false,
+            quantize: QuantizeMethod::default(),
+            dither: DitherMethod::default(),
+            chroma_key: None,
+            all_depths: false,
+            alpha_source: AlphaSource::default(),
+            stride_align: 1,
+            premultiply: false,
+            compress: CompressMethod::default(),
+        }
+    }
+
+    /// The color format used for `img`, resolving [`ColorFormat::Auto`].
+    #[must_use]
+    pub fn resolve_format(&self, img: &DynamicImage) -> ColorFormat {
+        match &self.format {
+            ColorFormat::Auto => format::detect(img, self.lvgl_version),
+            format => format.clone(),
+        }
+    }
+
+    fn params<'a>(
+        &'a self,
+        format: &'a ColorFormat,
+        alpha_source: AlphaSource,
+    ) -> GenerateParams<'a> {
+        GenerateParams {
+            var_name: &self.var_name,
+            format,
+            lvgl_version: self.lvgl_version,
+            big_endian: self.big_endian,
+            quantize: self.quantize,
+            dither: self.dither,
+            chroma_key: self.chroma_key,
+            all_depths: self.all_depths,
+            alpha_source,
+            stride_align: self.stride_align,
+            premultiply: self.premultiply,
+            compress: self.compress,
+            source_file: &self.source_file,
+            output_file: &self.output_file,
+        }
+    }
+}
+
+/// Generate the C source of an LVGL image descriptor for `img`.
+///
+/// # Errors
+///
+/// Fails if the image size or variable name is invalid, or the format or
+/// options are not supported by the LVGL version.
+pub fn convert(img: &DynamicImage, options: &ConvertOptions) -> Result<String> {
+    into_string(encode(img, options, codegen::generate)?)
+}
+
+/// Decode a PNG and generate the C source like [`convert`].
+///
+/// # Errors
+///
+/// Fails if `png` cannot be decoded, and for the reasons listed at
+/// [`convert`].
+pub fn convert_png(png: &[u8], options: &ConvertOptions) -> Result<String> {
+    let img = image::load_from_memory_with_format(png, image::ImageFormat::Png)?;
+    convert(&img, options)
+}
+
+/// Generate an LVGL binary image file (`.bin`) for `img`.
+///
+/// # Errors
+///
+/// Fails for the reasons listed at [`convert`], and for `all_depths`, which
+/// a binary image cannot hold.
+pub fn convert_bin(img: &DynamicImage, options: &ConvertOptions) -> Result<Vec<u8>> {
+    encode(img, options, codegen::generate_bin)
+}
+
+/// Generate a header declaring the descriptor [`convert`] writes, with
+/// `_WIDTH`, `_HEIGHT` and `_CF` defines.
+///
+/// The header is named after `output_file` with an `.h` extension.
+///
+/// # Errors
+///
+/// Fails if the image size or variable name is invalid.
+pub fn convert_header(img: &DynamicImage, options: &ConvertOptions) -> Result<String> {
+    check(img, options)?;
+    let format = options.resolve_format(img);
+    let params = options.params(&format, options.alpha_source.resolve(img));
+    let header = Path::new(&options.output_file).with_extension("h");
+    let file_name = header
+        .file_name()
+        .and_then(|s| s.to_str())
+        .unwrap_or("image.h");
+
+    let mut data = Vec::new();
+    codegen::generate_header(img, &mut data, &params, file_name)?;
+    into_string(data)
+}
+
+/// Resolve the options for `img` and run `generate` into a buffer.
+fn encode(
+    img: &DynamicImage,
+    options: &ConvertOptions,
+    generate: impl Fn(&DynamicImage, &mut Vec<u8>, &GenerateParams<'_>) -> Result<()>,
+) -> Result<Vec<u8>> {
+    check(img, options)?;
+    let format = options.resolve_format(img);
+    let alpha_source = options.alpha_source.resolve(img);
+
+    format::validate(img, &format, alpha_source);
+
+    let mut data = Vec::new();
+    generate(img, &mut data, &options.params(&format, alpha_source))?;
+    Ok(data)
+}
+
+fn check(img: &DynamicImage, options: &ConvertOptions) -> Result<()> {
+    let (w, h) = img.dimensions();
+    validation::validate_dimensions(w, h)?;
+    identifier::validate(&options.var_name)
+}
+
+fn into_string(data: Vec<u8>) -> Result<String> {
+    String::from_utf8(data)
+        .map_err(|e| Png2LvglError::Io(io::Error::new(io::ErrorKind::InvalidData, e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png(img: &DynamicImage) -> Vec<u8> {
+        let mut png = io::Cursor::new(Vec::new());
+        img.write_to(&mut png, image::ImageFormat::Png).unwrap();
+        png.into_inner()
+    }
+
+    #[test]
+    fn convert_png_matches_convert() {
+        let img = DynamicImage::new_rgb8(3, 2);
+        let options = ConvertOptions::new("logo");
+
+        let c_source = convert_png(&png(&img), &options).unwrap();
+        assert_eq!(c_source, convert(&img, &options).unwrap());
+        assert!(c_source.contains("const lv_image_dsc_t logo = {"));
+        assert!(c_source.contains("LV_COLOR_FORMAT_RGB565"));
+    }
+
+    #[test]
+    fn header_is_named_after_output_file() {
+        let img = DynamicImage::new_luma8(4, 4);
+        let mut options = ConvertOptions::new("icon");
+        options.output_file = "ui/icon.c".to_string();
+
+        let header = convert_header(&img, &options).unwrap();
+        assert!(header.contains("icon.h"));
+        assert!(header.contains("#ifndef PNG2LVGL_ICON_H\n#define PNG2LVGL_ICON_H\n"));
+        assert!(header.contains("#define ICON_WIDTH 4"));
+    }
+
+    #[test]
+    fn rejects_invalid_variable_names() {
+        let img = DynamicImage::new_rgb8(1, 1);
+        assert!(convert(&img, &ConvertOptions::new("2x")).is_err());
+        assert!(convert_bin(&img, &ConvertOptions::new("int")).is_err());
+    }
+
+    #[test]
+    fn all_depths_requires_lvgl_8() {
+        let img = DynamicImage::new_rgb8(2, 2);
+        let mut options = ConvertOptions::new("logo");
+        options.all_depths = true;
+
+        let Err(Png2LvglError::Config(message)) = convert(&img, &options) else {
+            panic!("expected a configuration error");
+        };
+        assert_eq!(message, "all-depths requires LVGL 8.x");
+
+        options.lvgl_version = LvglVersion::V8;
+        assert!(
+            convert(&img, &options)
+                .unwrap()
+                .contains("#if LV_COLOR_DEPTH")
+        );
+    }
+}
@@ -0,0 +1,385 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Fabian Schmieder
+
+use std::array;
+use std::sync::OnceLock;
+
+use tracing::debug;
+
+/// Error diffusion kernel entry: (dx, dy, weight).
+type KernelEntry = (isize, usize, f32);
+
+const FLOYD_STEINBERG: &[KernelEntry] = &[
+    (1, 0, 7.0 / 16.0),
+    (-1, 1, 3.0 / 16.0),
+    (0, 1, 5.0 / 16.0),
+    (1, 1, 1.0 / 16.0),
+];
+
+/// Atkinson diffuses only 6/8 of the error, trading accuracy for contrast.
+const ATKINSON: &[KernelEntry] = &[
+    (1, 0, 1.0 / 8.0),
+    (2, 0, 1.0 / 8.0),
+    (-1, 1, 1.0 / 8.0),
+    (0, 1, 1.0 / 8.0),
+    (1, 1, 1.0 / 8.0),
+    (0, 2, 1.0 / 8.0),
+];
+
+/// Side length of the tiled blue-noise threshold map.
+const BLUE_NOISE_SIZE: usize = 32;
+
+/// Gaussian sigma for the void-and-cluster energy function.
+const BLUE_NOISE_SIGMA: f32 = 1.5;
+
+/// Seed for the initial blue-noise pattern (kept fixed for reproducible output).
+const BLUE_NOISE_SEED: u32 = 0x2545_F491;
+
+/// Maximum sample value.
+const MAX_SAMPLE: f32 = 255.0;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, clap::ValueEnum)]
+pub enum DitherMethod {
+    #[default]
+    None,
+    FloydSteinberg,
+    Atkinson,
+    Bayer4,
+    Bayer8,
+    BlueNoise,
+}
+
+impl DitherMethod {
+    /// Name used in the C file header comment.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::FloydSteinberg => "Floyd-Steinberg",
+            Self::Atkinson => "Atkinson",
+            Self::Bayer4 => "ordered Bayer 4x4",
+            Self::Bayer8 => "ordered Bayer 8x8",
+            Self::BlueNoise => "blue noise",
+        }
+    }
+}
+
+/// Dither interleaved `N`-channel samples in place.
+///
+/// `quantize` maps a pixel to the nearest color representable by the target
+/// format. `step` is the distance between representable values per channel
+/// and scales the thresholds of ordered methods.
+pub fn dither<const N: usize>(
+    samples: &mut [u8],
+    width: u32,
+    method: DitherMethod,
+    step: [f32; N],
+    quantize: impl FnMut([f32; N]) -> [u8; N],
+) {
+    let width = width as usize;
+    debug!(?method, "Dithering");
+
+    match method {
+        DitherMethod::None => {}
+        DitherMethod::FloydSteinberg => diffuse(samples, width, FLOYD_STEINBERG, quantize),
+        DitherMethod::Atkinson => diffuse(samples, width, ATKINSON, quantize),
+        DitherMethod::Bayer4 => ordered(samples, width, step, quantize, |x, y| {
+            bayer_threshold(2, x, y)
+        }),
+        DitherMethod::Bayer8 => ordered(samples, width, step, quantize, |x, y| {
+            bayer_threshold(3, x, y)
+        }),
+        DitherMethod::BlueNoise => ordered(samples, width, step, quantize, blue_noise_threshold),
+    }
+}
+
+/// Round `value` to the nearest of `levels` evenly spaced values in 0..=255.
+///
+/// The result truncates back to the level index when shifted down to
+/// `log2(levels)` bits, so it survives the encoders' bit truncation.
+pub fn quantize_level(value: f32, levels: u16) -> u8 {
+    let max_level = f32::from(levels - 1);
+    let level = (value.clamp(0.0, MAX_SAMPLE) * max_level / MAX_SAMPLE).round();
+
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let quantized = (level * MAX_SAMPLE / max_level).round() as u8;
+    quantized
+}
+
+/// Distance between representable values for a channel with `levels` levels.
+pub fn level_step(levels: u16) -> f32 {
+    MAX_SAMPLE / f32::from(levels - 1)
+}
+
+fn diffuse<const N: usize>(
+    samples: &mut [u8],
+    width: usize,
+    kernel: &[KernelEntry],
+    mut quantize: impl FnMut([f32; N]) -> [u8; N],
+) {
+    let mut buffer: Vec<[f32; N]> = samples
+        .chunks_exact(N)
+        .map(|pixel| array::from_fn(|c| f32::from(pixel[c])))
+        .collect();
+    let height = buffer.len() / width.max(1);
+
+    for y in 0..height {
+        for x in 0..width {
+            let i = y * width + x;
+            let value = buffer[i].map(|v| v.clamp(0.0, MAX_SAMPLE));
+            let quantized = quantize(value);
+            samples[i * N..(i + 1) * N].copy_from_slice(&quantized);
+
+            let error: [f32; N] = array::from_fn(|c| value[c] - f32::from(quantized[c]));
+            for &(dx, dy, weight) in kernel {
+                let Some(nx) = x.checked_add_signed(dx) else {
+                    continue;
+                };
+                if nx >= width || y + dy >= height {
+                    continue;
+                }
+                let target = &mut buffer[(y + dy) * width + nx];
+                for (channel, err) in target.iter_mut().zip(error.iter()) {
+                    *channel += err * weight;
+                }
+            }
+        }
+    }
+}
+
+fn ordered<const N: usize>(
+    samples: &mut [u8],
+    width: usize,
+    step: [f32; N],
+    mut quantize: impl FnMut([f32; N]) -> [u8; N],
+    threshold: impl Fn(usize, usize) -> f32,
+) {
+    for (i, pixel) in samples.chunks_exact_mut(N).enumerate() {
+        let offset = threshold(i % width, i / width);
+        let value: [f32; N] = array::from_fn(|c| {
+            offset
+                .mul_add(step[c], f32::from(pixel[c]))
+                .clamp(0.0, MAX_SAMPLE)
+        });
+        pixel.copy_from_slice(&quantize(value));
+    }
+}
+
+/// Normalize a rank in `0..count` to a threshold in `-0.5..0.5`.
+#[allow(clippy::cast_precision_loss)]
+fn rank_threshold(rank: usize, count: usize) -> f32 {
+    (rank as f32 + 0.5) / count as f32 - 0.5
+}
+
+/// Threshold from a recursive Bayer matrix of size `2^bits`.
+fn bayer_threshold(bits: u32, x: usize, y: usize) -> f32 {
+    let rank = (0..bits).fold(0, |rank, bit| {
+        let (xb, yb) = ((x >> bit) & 1, (y >> bit) & 1);
+        (rank << 2) | ((xb ^ yb) << 1) | yb
+    });
+    rank_threshold(rank, 1 << (2 * bits))
+}
+
+fn blue_noise_threshold(x: usize, y: usize) -> f32 {
+    static RANKS: OnceLock<Vec<usize>> = OnceLock::new();
+    let ranks = RANKS.get_or_init(void_and_cluster);
+    let rank = ranks[(y % BLUE_NOISE_SIZE) * BLUE_NOISE_SIZE + x % BLUE_NOISE_SIZE];
+    rank_threshold(rank, ranks.len())
+}
+
+// ---------------------------------------------------------------------------
+// Blue-noise threshold map (Ulichney's void-and-cluster)
+// ---------------------------------------------------------------------------
+
+/// Binary pattern with its Gaussian energy field on a torus.
+struct Pattern {
+    ones: Vec<bool>,
+    energy: Vec<f32>,
+}
+
+impl Pattern {
+    fn set(&mut self, index: usize, on: bool, kernel: &[f32]) {
+        const S: usize = BLUE_NOISE_SIZE;
+        self.ones[index] = on;
+        let sign = if on { 1.0 } else { -1.0 };
+        let (ix, iy) = (index % S, index / S);
+        for (j, energy) in self.energy.iter_mut().enumerate() {
+            let dx = (j % S + S - ix) % S;
+            let dy = (j / S + S - iy) % S;
+            *energy += sign * kernel[dy * S + dx];
+        }
+    }
+
+    /// The set pixel with the most neighbors.
+    fn tightest_cluster(&self) -> usize {
+        self.extreme(true, |a, b| a > b)
+    }
+
+    /// The unset pixel with the fewest neighbors.
+    fn largest_void(&self) -> usize {
+        self.extreme(false, |a, b| a < b)
+    }
+
+    fn extreme(&self, on: bool, better: impl Fn(f32, f32) -> bool) -> usize {
+        let mut best: Option<usize> = None;
+        for (i, &energy) in self.energy.iter().enumerate() {
+            if self.ones[i] == on && best.is_none_or(|b| better(energy, self.energy[b])) {
+                best = Some(i);
+            }
+        }
+        best.unwrap_or(0)
+    }
+}
+
+fn void_and_cluster() -> Vec<usize> {
+    const S: usize = BLUE_NOISE_SIZE;
+    const AREA: usize = S * S;
+
+    #[allow(clippy::cast_precision_loss)]
+    let kernel: Vec<f32> = (0..AREA)
+        .map(|i| {
+            let (dx, dy) = (i % S, i / S);
+            let dx = dx.min(S - dx) as f32;
+            let dy = dy.min(S - dy) as f32;
+            (-dx.mul_add(dx, dy * dy) / (2.0 * BLUE_NOISE_SIGMA * BLUE_NOISE_SIGMA)).exp()
+        })
+        .collect();
+
+    let mut pattern = Pattern {
+        ones: vec![false; AREA],
+        energy: vec![0.0; AREA],
+    };
+
+    // Deterministic xorshift seed pattern with ~10% coverage
+    let mut state = BLUE_NOISE_SEED;
+    let mut initial = 0;
+    while initial < AREA / 10 {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        let index = state as usize % AREA;
+        if !pattern.ones[index] {
+            pattern.set(index, true, &kernel);
+            initial += 1;
+        }
+    }
+
+    // Move pixels from tightest clusters into largest voids until stable
+    for _ in 0..AREA {
+        let cluster = pattern.tightest_cluster();
+        pattern.set(cluster, false, &kernel);
+        let void = pattern.largest_void();
+        pattern.set(void, true, &kernel);
+        if void == cluster {
+            break;
+        }
+    }
+
+    let mut ranks = vec![0; AREA];
+
+    let mut removing = Pattern {
+        ones: pattern.ones.clone(),
+        energy: pattern.energy.clone(),
+    };
+    for rank in (0..initial).rev() {
+        let cluster = removing.tightest_cluster();
+        removing.set(cluster, false, &kernel);
+        ranks[cluster] = rank;
+    }
+
+    for rank in initial..AREA {
+        let void = pattern.largest_void();
+        pattern.set(void, true, &kernel);
+        ranks[void] = rank;
+    }
+
+    ranks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Mean of a flat gray image dithered to black and white.
+    fn dithered_mean(method: DitherMethod, gray: u8) -> f32 {
+        const SIZE: u32 = 32;
+        let mut samples = vec![gray; (SIZE * SIZE) as usize];
+        dither(&mut samples, SIZE, method, [level_step(2)], |value| {
+            [quantize_level(value[0], 2)]
+        });
+
+        #[allow(clippy::cast_precision_loss)]
+        let mean = samples.iter().map(|&s| f32::from(s)).sum::<f32>() / samples.len() as f32;
+        mean
+    }
+
+    /// Sorted thresholds of one `size` x `size` tile.
+    fn tile_thresholds(size: usize, threshold: impl Fn(usize, usize) -> f32) -> Vec<f32> {
+        let mut thresholds: Vec<f32> = (0..size * size)
+            .map(|i| threshold(i % size, i / size))
+            .collect();
+        thresholds.sort_by(f32::total_cmp);
+        thresholds
+    }
+
+    /// One threshold per rank, evenly spread over `-0.5..0.5`.
+    fn every_rank(count: usize) -> Vec<f32> {
+        (0..count).map(|rank| rank_threshold(rank, count)).collect()
+    }
+
+    #[test]
+    fn quantize_level_keeps_endpoints() {
+        for levels in [2, 4, 16, 32, 64, 256] {
+            assert_eq!(quantize_level(0.0, levels), 0);
+            assert_eq!(quantize_level(MAX_SAMPLE, levels), 255);
+            assert_eq!(quantize_level(-40.0, levels), 0);
+            assert_eq!(quantize_level(300.0, levels), 255);
+        }
+        assert_eq!(quantize_level(127.0, 2), 0);
+        assert_eq!(quantize_level(128.0, 2), 255);
+        assert_eq!(quantize_level(100.0, 256), 100);
+    }
+
+    #[test]
+    fn bayer_thresholds_cover_every_rank() {
+        let bayer4 = tile_thresholds(4, |x, y| bayer_threshold(2, x, y));
+        assert_eq!(bayer4, every_rank(16));
+        assert!(bayer4.iter().all(|t| (-0.5..0.5).contains(t)));
+
+        let bayer8 = tile_thresholds(8, |x, y| bayer_threshold(3, x, y));
+        assert_eq!(bayer8, every_rank(64));
+        assert!(bayer8.iter().all(|t| (-0.5..0.5).contains(t)));
+    }
+
+    #[test]
+    fn floyd_steinberg_preserves_mean_of_flat_gray() {
+        for gray in [64, 100, 128, 160, 192] {
+            let mean = dithered_mean(DitherMethod::FloydSteinberg, gray);
+            assert!((mean - f32::from(gray)).abs() < 2.0, "{gray}: {mean}");
+        }
+    }
+
+    #[test]
+    fn atkinson_preserves_mid_gray_and_adds_contrast() {
+        let mean = dithered_mean(DitherMethod::Atkinson, 128);
+        assert!((mean - 128.0).abs() < 2.0, "128: {mean}");
+
+        // The dropped quarter of the error pushes other grays outwards
+        for (gray, darker) in [(64, true), (100, true), (160, false), (192, false)] {
+            let mean = dithered_mean(DitherMethod::Atkinson, gray);
+            let drift = mean - f32::from(gray);
+            assert!(drift.abs() < 24.0, "{gray}: {mean}");
+            assert_eq!(drift < 0.0, darker, "{gray}: {mean}");
+        }
+    }
+
+    #[test]
+    fn blue_noise_ranks_are_a_permutation() {
+        let mut ranks = void_and_cluster();
+        ranks.sort_unstable();
+        assert_eq!(
+            ranks,
+            (0..BLUE_NOISE_SIZE * BLUE_NOISE_SIZE).collect::<Vec<_>>()
+        );
+    }
+}
@@ -61,22 +61,15 @@ pub enum ValidationError {
 
     #[error("Output file exists: {path}")]
     OutputExists { path: PathBuf },
+
+    #[error("Invalid C variable name '{name}': {reason}")]
+    InvalidIdentifier { name: String, reason: &'static str },
 }
 
 #[derive(Error, Debug)]
 pub enum FormatError {
-    #[error("Format {format} not implemented")]
-    NotImplemented { format: String },
-
-    #[error("Image has {colors} colors, exceeds {max_colors} for {format}")]
-    TooManyColors {
-        colors: usize,
-        max_colors: usize,
-        format: String,
-    },
-
-    #[error("Invalid bit depth {depth} for format {format}")]
-    InvalidBitDepth { depth: u8, format: String },
+    #[error("Format {format} is not supported by {version}")]
+    UnsupportedVersion { format: String, version: String },
 }
 
 pub type Result<T> = std::result::Result<T, Png2LvglError>;
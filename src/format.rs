@@ -3,29 +3,49 @@
 
 use std::collections::HashSet;
 
-use image::DynamicImage;
-use tracing::{debug, warn};
+use image::{DynamicImage, Rgba, RgbaImage};
+use tracing::{debug, info, warn};
 
-use crate::error::{FormatError, Result};
-
-/// Maximum unique colors before early-exit in `count_unique_colors`.
+/// Maximum unique colors before early-exit in `unique_colors`.
 const MAX_INDEXED_COLORS: usize = 256;
 
-/// Alpha bit-depth threshold multiplier for quality validation.
-const ALPHA_DEPTH_THRESHOLD: u16 = 4;
-
-#[derive(Copy, Clone, Debug, clap::ValueEnum)]
+#[derive(Copy, Clone, Debug, Hash, clap::ValueEnum)]
 pub enum LvglVersion {
     V8,
     V9,
 }
 
-#[derive(Clone, Debug, clap::ValueEnum)]
+impl LvglVersion {
+    /// Human-readable name for the C file header comment and error messages.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::V8 => "LVGL 8.x",
+            Self::V9 => "LVGL 9.x",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Hash, clap::ValueEnum)]
 pub enum ColorFormat {
     Auto,
     TrueColor,
     TrueColorAlpha,
     TrueColorChroma,
+    #[value(name = "rgb565-swapped")]
+    Rgb565Swapped,
+    #[value(name = "argb8565")]
+    Argb8565,
+    #[value(name = "argb8888")]
+    Argb8888,
+    #[value(name = "xrgb8888")]
+    Xrgb8888,
+    #[value(name = "rgb888")]
+    Rgb888,
+    #[value(name = "l8")]
+    L8,
+    #[value(name = "al88")]
+    Al88,
     Indexed1,
     Indexed2,
     Indexed4,
@@ -38,6 +58,7 @@ pub enum ColorFormat {
 
 impl ColorFormat {
     /// Returns the bits-per-pixel for indexed and alpha formats.
+    #[must_use]
     pub const fn bpp(&self) -> Option<u8> {
         match self {
             Self::Indexed1 | Self::Alpha1 => Some(1),
@@ -49,13 +70,17 @@ impl ColorFormat {
     }
 
     /// Returns the LVGL C constant name for this format.
+    #[must_use]
     pub const fn lvgl_const(&self, version: LvglVersion) -> &'static str {
         match version {
             LvglVersion::V8 => match self {
                 Self::Auto => "auto",
-                Self::TrueColor => "LV_IMG_CF_TRUE_COLOR",
-                Self::TrueColorAlpha => "LV_IMG_CF_TRUE_COLOR_ALPHA",
+                Self::TrueColor | Self::Rgb565Swapped | Self::Xrgb8888 => "LV_IMG_CF_TRUE_COLOR",
+                Self::TrueColorAlpha | Self::Argb8565 | Self::Argb8888 => {
+                    "LV_IMG_CF_TRUE_COLOR_ALPHA"
+                }
                 Self::TrueColorChroma => "LV_IMG_CF_TRUE_COLOR_CHROMA_KEYED",
+                Self::Rgb888 | Self::L8 | Self::Al88 => "LV_IMG_CF_UNKNOWN",
                 Self::Indexed1 => "LV_IMG_CF_INDEXED_1BIT",
                 Self::Indexed2 => "LV_IMG_CF_INDEXED_2BIT",
                 Self::Indexed4 => "LV_IMG_CF_INDEXED_4BIT",
@@ -67,9 +92,16 @@ impl ColorFormat {
             },
             LvglVersion::V9 => match self {
                 Self::Auto => "auto",
-                Self::TrueColor => "LV_COLOR_FORMAT_RGB565",
+                // LVGL 9 has no chroma-keyed format; the keyed data is plain RGB565
+                Self::TrueColor | Self::TrueColorChroma => "LV_COLOR_FORMAT_RGB565",
                 Self::TrueColorAlpha => "LV_COLOR_FORMAT_RGB565A8",
-                Self::TrueColorChroma => "LV_COLOR_FORMAT_RGB565_CHROMA_KEYED",
+                Self::Rgb565Swapped => "LV_COLOR_FORMAT_RGB565_SWAPPED",
+                Self::Argb8565 => "LV_COLOR_FORMAT_ARGB8565",
+                Self::Argb8888 => "LV_COLOR_FORMAT_ARGB8888",
+                Self::Xrgb8888 => "LV_COLOR_FORMAT_XRGB8888",
+                Self::Rgb888 => "LV_COLOR_FORMAT_RGB888",
+                Self::L8 => "LV_COLOR_FORMAT_L8",
+                Self::Al88 => "LV_COLOR_FORMAT_AL88",
                 Self::Indexed1 => "LV_COLOR_FORMAT_I1",
                 Self::Indexed2 => "LV_COLOR_FORMAT_I2",
                 Self::Indexed4 => "LV_COLOR_FORMAT_I4",
@@ -82,7 +114,50 @@ impl ColorFormat {
         }
     }
 
+    /// Numeric color format for the binary image header, matching
+    /// [`lvgl_const`](Self::lvgl_const).
+    #[must_use]
+    pub const fn lvgl_code(&self, version: LvglVersion) -> u8 {
+        match version {
+            LvglVersion::V8 => match self {
+                Self::Auto | Self::Rgb888 | Self::L8 | Self::Al88 => 0,
+                Self::TrueColor | Self::Rgb565Swapped | Self::Xrgb8888 => 4,
+                Self::TrueColorAlpha | Self::Argb8565 | Self::Argb8888 => 5,
+                Self::TrueColorChroma => 6,
+                Self::Indexed1 => 7,
+                Self::Indexed2 => 8,
+                Self::Indexed4 => 9,
+                Self::Indexed8 => 10,
+                Self::Alpha1 => 11,
+                Self::Alpha2 => 12,
+                Self::Alpha4 => 13,
+                Self::Alpha8 => 14,
+            },
+            LvglVersion::V9 => match self {
+                Self::Auto => 0,
+                Self::L8 => 0x06,
+                Self::Indexed1 => 0x07,
+                Self::Indexed2 => 0x08,
+                Self::Indexed4 => 0x09,
+                Self::Indexed8 => 0x0A,
+                Self::Alpha1 => 0x0B,
+                Self::Alpha2 => 0x0C,
+                Self::Alpha4 => 0x0D,
+                Self::Alpha8 => 0x0E,
+                Self::Rgb888 => 0x0F,
+                Self::Argb8888 => 0x10,
+                Self::Xrgb8888 => 0x11,
+                Self::TrueColor | Self::TrueColorChroma => 0x12,
+                Self::Argb8565 => 0x13,
+                Self::TrueColorAlpha => 0x14,
+                Self::Al88 => 0x15,
+                Self::Rgb565Swapped => 0x1B,
+            },
+        }
+    }
+
     /// Human-readable description for the C file header comment.
+    #[must_use]
     pub const fn description(&self) -> &'static str {
         match self {
             Self::Indexed1 => "1-bit indexed (2 colors)",
@@ -96,92 +171,195 @@ impl ColorFormat {
             Self::TrueColor => "RGB565 true color",
             Self::TrueColorAlpha => "RGB565 true color + alpha",
             Self::TrueColorChroma => "RGB565 true color + chroma key",
+            Self::Rgb565Swapped => "RGB565 true color, byte-swapped",
+            Self::Argb8565 => "RGB565 true color + interleaved alpha",
+            Self::Argb8888 => "ARGB8888 true color + alpha",
+            Self::Xrgb8888 => "XRGB8888 true color",
+            Self::Rgb888 => "RGB888 true color",
+            Self::L8 => "8-bit luminance",
+            Self::Al88 => "8-bit luminance + alpha",
             Self::Auto => "Auto-detected",
         }
     }
 
     /// Whether this format uses RGB565 encoding (affected by endianness).
+    ///
+    /// `Rgb565Swapped` is excluded: its byte order is fixed.
+    #[must_use]
     pub const fn is_rgb565(&self) -> bool {
-        matches!(self, Self::TrueColor | Self::TrueColorAlpha)
+        matches!(
+            self,
+            Self::TrueColor | Self::TrueColorAlpha | Self::TrueColorChroma | Self::Argb8565
+        )
     }
 
     /// Whether this format includes an alpha channel.
+    #[must_use]
     pub const fn has_alpha(&self) -> bool {
-        matches!(self, Self::TrueColorAlpha)
+        matches!(
+            self,
+            Self::TrueColorAlpha | Self::Argb8565 | Self::Argb8888 | Self::Al88
+        )
     }
-}
 
-/// Auto-detect the best color format based on image properties.
-pub fn detect(img: &DynamicImage) -> ColorFormat {
-    if img.color().has_alpha() {
-        ColorFormat::TrueColorAlpha
-    } else {
-        ColorFormat::TrueColor
+    /// Whether color data can be stored premultiplied by alpha
+    /// (`LV_IMAGE_FLAGS_PREMULTIPLIED`).
+    #[must_use]
+    pub const fn can_premultiply(&self) -> bool {
+        matches!(self, Self::TrueColorAlpha | Self::Argb8565 | Self::Argb8888)
+    }
+
+    /// Whether this format can be used with the given LVGL version.
+    ///
+    /// LVGL 8 has no 24-bit or grayscale formats; its 32-bit formats require
+    /// `LV_COLOR_DEPTH 32`.
+    #[must_use]
+    pub const fn supports(&self, version: LvglVersion) -> bool {
+        !matches!(
+            (self, version),
+            (Self::Rgb888 | Self::L8 | Self::Al88, LvglVersion::V8)
+        )
     }
 }
 
-/// Validate that the image is compatible with the chosen format.
-pub fn validate(img: &DynamicImage, format: &ColorFormat) -> Result<()> {
-    debug!(?format, "Validating format compatibility");
+/// Channel that alpha-only formats take their coverage values from.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, clap::ValueEnum)]
+pub enum AlphaSource {
+    /// Alpha channel if the image has one, luminance otherwise
+    #[default]
+    Auto,
+    Alpha,
+    Luminance,
+    InvertedLuminance,
+    Red,
+    Green,
+    Blue,
+}
 
-    match format {
-        ColorFormat::Indexed1
-        | ColorFormat::Indexed2
-        | ColorFormat::Indexed4
-        | ColorFormat::Indexed8 => validate_indexed(img, format),
-        ColorFormat::Alpha1 | ColorFormat::Alpha2 | ColorFormat::Alpha4 | ColorFormat::Alpha8 => {
-            validate_alpha(img, format)
+impl AlphaSource {
+    /// Resolve `Auto` to a concrete channel for the given image.
+    #[must_use]
+    pub fn resolve(self, img: &DynamicImage) -> Self {
+        match self {
+            Self::Auto if img.color().has_alpha() => Self::Alpha,
+            Self::Auto => Self::Luminance,
+            source => source,
+        }
+    }
+
+    /// Name used in the C file header comment and log messages.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Auto => "auto",
+            Self::Alpha => "alpha channel",
+            Self::Luminance => "luminance",
+            Self::InvertedLuminance => "inverted luminance",
+            Self::Red => "red channel",
+            Self::Green => "green channel",
+            Self::Blue => "blue channel",
         }
-        _ => Ok(()),
     }
 }
 
-fn validate_indexed(img: &DynamicImage, format: &ColorFormat) -> Result<()> {
-    let bpp = format.bpp().expect("indexed format must have bpp");
-    let max_colors = 1_usize << bpp;
-    let unique_colors = count_unique_colors(img);
+/// Parse an `RRGGBB` hex color, optionally prefixed with `#` or `0x`.
+///
+/// # Errors
+///
+/// Returns a message for anything but six hex digits.
+pub fn parse_hex_color(value: &str) -> std::result::Result<[u8; 3], String> {
+    let hex = value
+        .strip_prefix('#')
+        .or_else(|| value.strip_prefix("0x"))
+        .unwrap_or(value);
 
-    debug!(unique_colors, max_colors, "Checking color count");
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("expected a color as RRGGBB hex, got '{value}'"));
+    }
 
-    if unique_colors > max_colors {
-        return Err(FormatError::TooManyColors {
-            colors: unique_colors,
-            max_colors,
-            format: format!("{format:?}"),
-        }
-        .into());
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|e| e.to_string());
+    Ok([channel(0)?, channel(2)?, channel(4)?])
+}
+
+/// Parse a row alignment in bytes, which must be a power of two.
+///
+/// # Errors
+///
+/// Returns a message if `value` is not a power of two.
+pub fn parse_stride_align(value: &str) -> std::result::Result<usize, String> {
+    match value.parse::<usize>() {
+        Ok(align) if align.is_power_of_two() => Ok(align),
+        _ => Err(format!("expected a power of two in bytes, got '{value}'")),
     }
-    Ok(())
 }
 
-fn validate_alpha(img: &DynamicImage, format: &ColorFormat) -> Result<()> {
-    let bpp = format.bpp().expect("alpha format must have bpp");
+/// Auto-detect the best color format based on image properties.
+///
+/// Grayscale images use the luminance formats where the LVGL version has them.
+pub fn detect(img: &DynamicImage, version: LvglVersion) -> ColorFormat {
+    let color = img.color();
+    let grayscale = !color.has_color() && matches!(version, LvglVersion::V9);
 
-    if img.color().has_color() {
-        warn!("Converting color image to alpha-only format");
+    match (grayscale, color.has_alpha()) {
+        (true, true) => ColorFormat::Al88,
+        (true, false) => ColorFormat::L8,
+        (false, true) => ColorFormat::TrueColorAlpha,
+        (false, false) => ColorFormat::TrueColor,
     }
+}
 
-    let img_bits = img.color().bits_per_pixel();
-    if bpp < 8 && img_bits > u16::from(bpp) * ALPHA_DEPTH_THRESHOLD {
-        return Err(FormatError::InvalidBitDepth {
-            depth: bpp,
-            format: format!("{format:?}"),
+/// Report how the image is adapted to the chosen format.
+pub fn validate(img: &DynamicImage, format: &ColorFormat, alpha_source: AlphaSource) {
+    debug!(?format, "Validating format compatibility");
+
+    // Indexed formats report quantization while encoding, where the colors
+    // are counted anyway
+    match format {
+        ColorFormat::Alpha1 | ColorFormat::Alpha2 | ColorFormat::Alpha4 | ColorFormat::Alpha8 => {
+            check_alpha_source(img, alpha_source);
+        }
+        ColorFormat::L8 | ColorFormat::Al88 if img.color().has_color() => {
+            warn!("Converting color image to grayscale");
+        }
+        _ => {}
+    }
+}
+
+/// Tell the user which channel coverage is read from, and when that
+/// channel carries no useful coverage.
+///
+/// Reducing the 8-bit channel to fewer bits is what the alpha formats are
+/// for, so the image's own bit depth is not checked.
+fn check_alpha_source(img: &DynamicImage, source: AlphaSource) {
+    let source = source.resolve(img);
+    info!(source = source.name(), "Alpha source");
+
+    match source {
+        AlphaSource::Alpha if !img.color().has_alpha() => {
+            warn!("Image has no alpha channel; alpha data will be fully opaque");
+        }
+        AlphaSource::Luminance | AlphaSource::InvertedLuminance if img.color().has_color() => {
+            warn!("Converting color image to alpha-only format");
         }
-        .into());
+        _ => {}
     }
-    Ok(())
 }
 
-fn count_unique_colors(img: &DynamicImage) -> usize {
-    let rgba = img.to_rgba8();
-    let mut colors = HashSet::new();
+/// Collect the unique RGBA colors of an image in order of first appearance.
+///
+/// Stops early once more than `MAX_INDEXED_COLORS` colors have been seen.
+pub fn unique_colors(rgba: &RgbaImage) -> Vec<Rgba<u8>> {
+    let mut seen = HashSet::new();
+    let mut colors = Vec::new();
 
     for pixel in rgba.pixels() {
-        colors.insert((pixel[0], pixel[1], pixel[2]));
-        if colors.len() > MAX_INDEXED_COLORS {
-            return colors.len();
+        if seen.insert(*pixel) {
+            colors.push(*pixel);
+            if colors.len() > MAX_INDEXED_COLORS {
+                break;
+            }
         }
     }
 
-    colors.len()
+    colors
 }
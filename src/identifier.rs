@@ -0,0 +1,245 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Fabian Schmieder
+
+use tracing::debug;
+
+use crate::error::{Result, ValidationError};
+
+/// Name used when nothing usable is left of the file name.
+const FALLBACK_NAME: &str = "image";
+
+/// Prefix for names that would otherwise start with a digit.
+const DIGIT_PREFIX: &str = "img_";
+
+/// C and C++ keywords (including alternative operator spellings), which are
+/// not usable as variable names.
+const KEYWORDS: &[&str] = &[
+    // C
+    "_Alignas",
+    "_Alignof",
+    "_Atomic",
+    "_BitInt",
+    "_Bool",
+    "_Complex",
+    "_Decimal128",
+    "_Decimal32",
+    "_Decimal64",
+    "_Generic",
+    "_Imaginary",
+    "_Noreturn",
+    "_Static_assert",
+    "_Thread_local",
+    "alignas",
+    "alignof",
+    "auto",
+    "bool",
+    "break",
+    "case",
+    "char",
+    "const",
+    "constexpr",
+    "continue",
+    "default",
+    "do",
+    "double",
+    "else",
+    "enum",
+    "extern",
+    "false",
+    "float",
+    "for",
+    "goto",
+    "if",
+    "inline",
+    "int",
+    "long",
+    "nullptr",
+    "register",
+    "restrict",
+    "return",
+    "short",
+    "signed",
+    "sizeof",
+    "static",
+    "static_assert",
+    "struct",
+    "switch",
+    "thread_local",
+    "true",
+    "typedef",
+    "typeof",
+    "typeof_unqual",
+    "union",
+    "unsigned",
+    "void",
+    "volatile",
+    "while",
+    // C++
+    "and",
+    "and_eq",
+    "asm",
+    "bitand",
+    "bitor",
+    "catch",
+    "char16_t",
+    "char32_t",
+    "char8_t",
+    "class",
+    "co_await",
+    "co_return",
+    "co_yield",
+    "compl",
+    "concept",
+    "const_cast",
+    "consteval",
+    "constinit",
+    "decltype",
+    "delete",
+    "dynamic_cast",
+    "explicit",
+    "export",
+    "friend",
+    "mutable",
+    "namespace",
+    "new",
+    "noexcept",
+    "not",
+    "not_eq",
+    "operator",
+    "or",
+    "or_eq",
+    "private",
+    "protected",
+    "public",
+    "reinterpret_cast",
+    "requires",
+    "static_cast",
+    "template",
+    "this",
+    "throw",
+    "try",
+    "typeid",
+    "typename",
+    "using",
+    "virtual",
+    "wchar_t",
+    "xor",
+    "xor_eq",
+];
+
+/// Turn an arbitrary file stem into a valid C identifier.
+///
+/// Runs of characters outside `[A-Za-z0-9_]` (spaces, dots, dashes, non-ASCII)
+/// become a single underscore, a leading digit gets an `img_` prefix and
+/// keywords get a trailing underscore.
+pub fn sanitize(name: &str) -> String {
+    let mut sanitized = String::with_capacity(name.len());
+    let mut replaced = false;
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() || c == '_' {
+            sanitized.push(c);
+            replaced = false;
+        } else if !replaced {
+            sanitized.push('_');
+            replaced = true;
+        }
+    }
+
+    if sanitized.is_empty() || sanitized.chars().all(|c| c == '_') {
+        sanitized = FALLBACK_NAME.to_string();
+    }
+    if sanitized.starts_with(|c: char| c.is_ascii_digit()) {
+        sanitized.insert_str(0, DIGIT_PREFIX);
+    }
+    if is_keyword(&sanitized) {
+        sanitized.push('_');
+    }
+
+    debug!(name, sanitized, "Sanitized variable name");
+    sanitized
+}
+
+/// Check that `name` can be used as a C variable name as-is.
+///
+/// # Errors
+///
+/// Fails with [`ValidationError::InvalidIdentifier`] for empty names,
+/// keywords and names that are not valid C identifiers.
+pub fn validate(name: &str) -> Result<()> {
+    let reason = if name.is_empty() {
+        Some("must not be empty")
+    } else if is_keyword(name) {
+        Some("is a C or C++ keyword")
+    } else {
+        invalid_start(name)
+    };
+    reason.map_or(Ok(()), |reason| invalid(name, reason))
+}
+
+/// Check that `prefix` can start a C variable name.
+///
+/// Keywords are fine here since the file name is appended.
+///
+/// # Errors
+///
+/// Fails with [`ValidationError::InvalidIdentifier`] if the prefix starts
+/// with a digit or contains characters other than ASCII letters, digits and
+/// underscores.
+pub fn validate_prefix(prefix: &str) -> Result<()> {
+    invalid_start(prefix).map_or(Ok(()), |reason| invalid(prefix, reason))
+}
+
+fn invalid_start(name: &str) -> Option<&'static str> {
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        Some("must not start with a digit")
+    } else if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        Some("may only contain ASCII letters, digits and underscores")
+    } else {
+        None
+    }
+}
+
+fn invalid(name: &str, reason: &'static str) -> Result<()> {
+    Err(ValidationError::InvalidIdentifier {
+        name: name.to_string(),
+        reason,
+    }
+    .into())
+}
+
+fn is_keyword(name: &str) -> bool {
+    KEYWORDS.contains(&name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitize_replaces_invalid_characters() {
+        assert_eq!(sanitize("2x icon.v2"), "img_2x_icon_v2");
+        assert_eq!(sanitize("my-icon"), "my_icon");
+        assert_eq!(sanitize("größe"), "gr_e");
+        assert_eq!(sanitize("..."), "image");
+        assert_eq!(sanitize("int"), "int_");
+        assert_eq!(sanitize("class"), "class_");
+    }
+
+    #[test]
+    fn validate_rejects_invalid_names() {
+        assert!(validate("logo_dark").is_ok());
+        assert!(validate("_logo").is_ok());
+        assert!(validate("").is_err());
+        assert!(validate("2x").is_err());
+        assert!(validate("logo.dark").is_err());
+        assert!(validate("switch").is_err());
+    }
+
+    #[test]
+    fn validate_prefix_allows_keywords() {
+        assert!(validate_prefix("").is_ok());
+        assert!(validate_prefix("img_").is_ok());
+        assert!(validate_prefix("int").is_ok());
+        assert!(validate_prefix("1_").is_err());
+    }
+}
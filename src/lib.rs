@@ -0,0 +1,53 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Fabian Schmieder
+
+//! Convert PNG images to LVGL image descriptors.
+//!
+//! [`convert`] turns a decoded image into the C source of an LVGL image
+//! descriptor, [`convert_bin`] into an LVGL binary image file and
+//! [`convert_header`] into a header declaring the descriptor. Everything the
+//! output depends on is set in [`ConvertOptions`]:
+//!
+//! ```
+//! use png2lvgl::{ColorFormat, ConvertOptions, DynamicImage, LvglVersion};
+//!
+//! let img = DynamicImage::new_rgba8(16, 16);
+//! let mut options = ConvertOptions::new("logo");
+//! options.format = ColorFormat::TrueColorAlpha;
+//! options.lvgl_version = LvglVersion::V8;
+//!
+//! let c_source = png2lvgl::convert(&img, &options)?;
+//! assert!(c_source.contains("const lv_img_dsc_t logo"));
+//! # Ok::<(), png2lvgl::Png2LvglError>(())
+//! ```
+//!
+//! Build scripts convert a project's images with [`Assets`], which writes C
+//! sources or Rust statics into `OUT_DIR`.
+
+mod assets;
+mod codegen;
+mod compress;
+mod convert;
+mod dither;
+mod error;
+mod format;
+pub mod identifier;
+mod quantize;
+mod validation;
+
+pub use assets::{Assets, Emit, RUST_FILE};
+pub use compress::CompressMethod;
+pub use convert::{ConvertOptions, convert, convert_bin, convert_header, convert_png};
+pub use dither::DitherMethod;
+pub use error::{FormatError, Png2LvglError, Result, ValidationError};
+pub use format::{AlphaSource, ColorFormat, LvglVersion, parse_hex_color, parse_stride_align};
+pub use image::DynamicImage;
+pub use quantize::QuantizeMethod;
+pub use validation::validate_input_file;
+
+/// Version of the converter, including the git revision when built from a
+/// checkout.
+#[must_use]
+pub fn version() -> &'static str {
+    codegen::built_info::GIT_VERSION.unwrap_or(codegen::built_info::PKG_VERSION)
+}
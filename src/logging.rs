@@ -0,0 +1,52 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Fabian Schmieder
+
+use std::cell::RefCell;
+use std::io::{self, Write};
+
+thread_local! {
+    /// Log output held back for the file this thread is converting.
+    static BUFFER: RefCell<Option<Vec<u8>>> = const { RefCell::new(None) };
+}
+
+/// Install the global subscriber, writing to stderr (`RUST_LOG` overrides
+/// the default `info` level).
+pub fn init() {
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info")),
+        )
+        .with_writer(|| LogWriter)
+        .init();
+}
+
+/// Run `f` with this thread's log output held back, then write it to stderr
+/// in one piece so that parallel conversions do not interleave.
+pub fn buffered<T>(f: impl FnOnce() -> T) -> T {
+    let previous = BUFFER.replace(Some(Vec::new()));
+    let result = f();
+    let logs = BUFFER.replace(previous).unwrap_or_default();
+
+    let _ = io::stderr().lock().write_all(&logs);
+    result
+}
+
+/// Writes to the thread's buffer while [`buffered`] runs, to stderr otherwise.
+struct LogWriter;
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        BUFFER.with_borrow_mut(|buffer| {
+            let Some(buffer) = buffer else {
+                return io::stderr().write(buf);
+            };
+            buffer.extend_from_slice(buf);
+            Ok(buf.len())
+        })
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stderr().flush()
+    }
+}
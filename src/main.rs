@@ -1,35 +1,93 @@
 // SPDX-License-Identifier: GPL-3.0-only
 // Copyright (C) 2025 Fabian Schmieder
 
-use std::fs::File;
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use clap::Parser;
 use image::GenericImageView;
-use tracing::{error, info, instrument, warn};
-
-mod codegen;
-mod error;
-mod format;
-mod validation;
-
-use codegen::GenerateParams;
-use error::{Png2LvglError, Result};
-use format::{ColorFormat, LvglVersion};
-
-#[derive(Parser)]
+use png2lvgl::{
+    AlphaSource, ColorFormat, CompressMethod, ConvertOptions, DitherMethod, LvglVersion,
+    Png2LvglError, QuantizeMethod, Result, ValidationError, identifier,
+};
+use rayon::prelude::*;
+use tracing::{debug, error, info, instrument, warn};
+
+mod batch;
+mod cache;
+mod logging;
+mod manifest;
+mod watch;
+
+use batch::{InputFile, Job};
+use cache::Cache;
+
+#[derive(Clone, Parser)]
 #[command(name = "png2lvgl")]
-#[command(version = codegen::built_info::GIT_VERSION.unwrap_or(codegen::built_info::PKG_VERSION))]
+#[command(version = png2lvgl::version())]
 #[command(about = "Convert PNG images to LVGL C arrays", long_about = None)]
 #[allow(clippy::struct_excessive_bools)]
 struct Args {
-    /// Input PNG file
-    input: PathBuf,
+    /// Input PNG files, directories or glob patterns
+    #[arg(required_unless_present = "manifest", value_name = "INPUT")]
+    inputs: Vec<PathBuf>,
+
+    /// Convert every image listed in a TOML asset manifest
+    #[arg(
+        short,
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["inputs", "output", "stdout", "var_name"]
+    )]
+    manifest: Option<PathBuf>,
+
+    /// Keep running and convert inputs again whenever they or the manifest
+    /// change (implies --overwrite)
+    #[arg(short, long, conflicts_with = "stdout")]
+    watch: bool,
+
+    /// Also convert PNGs in subdirectories of input directories
+    #[arg(short, long)]
+    recursive: bool,
 
-    /// Output C file (defaults to input filename with .c extension)
+    /// Output file (defaults to input filename with .c or .bin extension)
     #[arg(short, long)]
     output: Option<PathBuf>,
 
+    /// Number of files converted in parallel (defaults to the number of CPUs)
+    #[arg(short, long, value_name = "N")]
+    jobs: Option<NonZeroUsize>,
+
+    /// Remember conversions in this file and skip inputs whose PNG and
+    /// options did not change since
+    #[arg(long, value_name = "FILE")]
+    cache: Option<PathBuf>,
+
+    /// Write outputs into this directory, mirroring the input tree
+    #[arg(long, value_name = "DIR", conflicts_with_all = ["output", "stdout"])]
+    output_dir: Option<PathBuf>,
+
+    /// Write an LVGL binary image file (.bin) instead of a C array
+    #[arg(long, conflicts_with = "all_depths")]
+    bin: bool,
+
+    /// Also write a companion .h declaring the image next to the .c file
+    #[arg(long, conflicts_with_all = ["stdout", "bin"])]
+    header: bool,
+
+    /// C variable name (defaults to the output file name, sanitized)
+    #[arg(long, value_name = "NAME")]
+    var_name: Option<String>,
+
+    /// Prefix for the C variable name (e.g. `img_`)
+    #[arg(long, value_name = "PREFIX", default_value = "")]
+    var_prefix: String,
+
     /// Write to stdout instead of file
     #[arg(long)]
     stdout: bool,
@@ -53,6 +111,50 @@ struct Args {
     /// Generate big-endian RGB565 (for big-endian systems)
     #[arg(long)]
     big_endian: bool,
+
+    /// Color quantization algorithm for indexed formats with too many colors
+    #[arg(long, value_enum, default_value = "median-cut")]
+    quantize: QuantizeMethod,
+
+    /// Dithering applied before reducing color or alpha depth
+    #[arg(long, value_enum, default_value = "none")]
+    dither: DitherMethod,
+
+    /// Chroma key color as RRGGBB hex (true-color-chroma only; derived from
+    /// fully transparent pixels if omitted)
+    #[arg(long, value_name = "RRGGBB", value_parser = png2lvgl::parse_hex_color)]
+    chroma_key: Option<[u8; 3]>,
+
+    /// Emit every `LV_COLOR_DEPTH` variant of true color formats inside
+    /// preprocessor guards (LVGL 8.x only)
+    #[arg(long, requires = "lvgl_v8")]
+    all_depths: bool,
+
+    /// Channel that alpha-only formats read coverage from (alpha channel if
+    /// the image has one, luminance otherwise)
+    #[arg(long, value_enum, default_value = "auto")]
+    alpha_source: AlphaSource,
+
+    /// Pad every row to a multiple of this many bytes (power of two, LVGL 9.x
+    /// only)
+    #[arg(
+        long,
+        value_name = "BYTES",
+        default_value = "1",
+        value_parser = png2lvgl::parse_stride_align,
+        conflicts_with = "lvgl_v8"
+    )]
+    stride_align: usize,
+
+    /// Store color premultiplied by alpha (true-color-alpha, argb8565,
+    /// argb8888; LVGL 9.x only)
+    #[arg(long, conflicts_with = "lvgl_v8")]
+    premultiply: bool,
+
+    /// Compress the image data (LVGL 9.x only; needs `LV_USE_RLE` or
+    /// `LV_USE_LZ4` at runtime)
+    #[arg(long, value_enum, default_value = "none", conflicts_with = "lvgl_v8")]
+    compress: CompressMethod,
 }
 
 impl Args {
@@ -65,13 +167,87 @@ impl Args {
     }
 }
 
-fn main() -> Result<()> {
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info")),
+/// C variable name from `--var-name` or the output file stem, with the prefix.
+fn variable_name(args: &Args, input: &Path, output: Option<&Path>) -> Result<String> {
+    identifier::validate_prefix(&args.var_prefix)?;
+
+    if let Some(name) = &args.var_name {
+        let name = format!("{}{name}", args.var_prefix);
+        identifier::validate(&name)?;
+        return Ok(name);
+    }
+
+    let stem = output
+        .and_then(|p| p.file_stem())
+        .or_else(|| input.file_stem())
+        .map_or_else(|| "image".into(), |s| s.to_string_lossy());
+    Ok(identifier::sanitize(&format!("{}{stem}", args.var_prefix)))
+}
+
+/// Library options for converting `input` to `output`.
+fn convert_options(
+    args: &Args,
+    var_name: String,
+    input: &Path,
+    output: Option<&Path>,
+) -> ConvertOptions {
+    let file_name = |path: Option<&Path>, fallback: &str| {
+        path.and_then(Path::file_name).map_or_else(
+            || fallback.to_string(),
+            |s| s.to_string_lossy().into_owned(),
         )
-        .init();
+    };
+
+    let mut options = ConvertOptions::new(var_name);
+    options.format = args.format.clone();
+    options.lvgl_version = args.lvgl_version();
+    options.big_endian = args.big_endian;
+    options.quantize = args.quantize;
+    options.dither = args.dither;
+    options.chroma_key = args.chroma_key;
+    options.all_depths = args.all_depths;
+    options.alpha_source = args.alpha_source;
+    options.stride_align = args.stride_align;
+    options.premultiply = args.premultiply;
+    options.compress = args.compress;
+    options.source_file = file_name(Some(input), "unknown.png");
+    options.output_file = file_name(output, "stdout");
+    options
+}
+
+/// Warn about options that have no effect for the selected format.
+fn warn_ignored_options(args: &Args, fmt: &ColorFormat) {
+    if args.big_endian && matches!(fmt, ColorFormat::Rgb565Swapped) {
+        warn!("--big-endian flag ignored: rgb565-swapped is always byte-swapped");
+    } else if args.big_endian && !fmt.is_rgb565() {
+        warn!("--big-endian flag ignored: only applies to RGB565 formats");
+    }
+
+    if args.all_depths && !fmt.is_rgb565() {
+        warn!("--all-depths flag ignored: only applies to true color formats");
+    } else if args.all_depths && args.big_endian {
+        warn!("--big-endian flag ignored: LV_COLOR_16_SWAP selects the byte order");
+    }
+
+    if args.chroma_key.is_some() && !matches!(fmt, ColorFormat::TrueColorChroma) {
+        warn!("--chroma-key ignored: only applies to true-color-chroma");
+    }
+
+    let is_alpha_format = matches!(
+        fmt,
+        ColorFormat::Alpha1 | ColorFormat::Alpha2 | ColorFormat::Alpha4 | ColorFormat::Alpha8
+    );
+    if args.alpha_source != AlphaSource::Auto && !is_alpha_format {
+        warn!("--alpha-source ignored: only applies to alpha formats");
+    }
+
+    if args.premultiply && !fmt.can_premultiply() {
+        warn!("--premultiply ignored: only applies to formats with color and alpha");
+    }
+}
+
+fn main() -> Result<ExitCode> {
+    logging::init();
 
     let result = run();
     if let Err(ref e) = result {
@@ -80,10 +256,11 @@ fn main() -> Result<()> {
     result
 }
 
+/// Fails for errors that stop all conversions; files that fail in a batch
+/// are reported and turn into a failure exit code.
 #[instrument(skip_all)]
-fn run() -> Result<()> {
+fn run() -> Result<ExitCode> {
     let args = Args::parse();
-    let lvgl_version = args.lvgl_version();
 
     if args.stdout && args.output.is_some() {
         return Err(Png2LvglError::Config(
@@ -91,86 +268,342 @@ fn run() -> Result<()> {
         ));
     }
 
-    validation::validate_input_file(&args.input)?;
+    if args.watch {
+        watch::run(&args);
+    }
 
-    let output = if args.stdout {
-        None
+    let cache = args.cache.as_deref().map(Cache::load);
+    let jobs = collect_jobs(&args)?;
+    let failed = convert_jobs(&args, &jobs, cache.as_ref());
+    if let Some(cache) = cache {
+        cache.save()?;
+    }
+    Ok(if failed? == 0 {
+        ExitCode::SUCCESS
     } else {
-        Some(
-            args.output
-                .unwrap_or_else(|| args.input.with_extension("c")),
-        )
+        ExitCode::FAILURE
+    })
+}
+
+/// Jobs from the manifest, or one per file named on the command line.
+fn collect_jobs(args: &Args) -> Result<Vec<Job>> {
+    let mut jobs = if let Some(ref path) = args.manifest {
+        manifest::load(path, args)?
+    } else {
+        let files = batch::collect_inputs(&args.inputs, args.recursive)?;
+        if files.is_empty() {
+            return Err(Png2LvglError::Config("No PNG files to convert".to_string()));
+        }
+        if files.len() > 1 {
+            check_batch_options(args)?;
+        }
+        files
+            .into_iter()
+            .map(|file| Job {
+                args: args.clone(),
+                file,
+            })
+            .collect()
     };
 
-    if let Some(ref path) = output {
-        validation::validate_output_path(path, args.overwrite)?;
+    // Watch mode regenerates its own outputs
+    for job in &mut jobs {
+        job.args.overwrite |= job.args.watch;
+    }
+    check_distinct_outputs(&jobs)?;
+    Ok(jobs)
+}
+
+/// Refuse jobs that would write the same file, since which of them wins
+/// would depend on thread scheduling.
+fn check_distinct_outputs(jobs: &[Job]) -> Result<()> {
+    let mut writers: HashMap<PathBuf, &Path> = HashMap::new();
+    for job in jobs {
+        let Some(output) = output_path(&job.args, &job.file) else {
+            continue;
+        };
+        let header = job.args.header.then(|| output.with_extension("h"));
+        for path in std::iter::once(output).chain(header) {
+            if let Some(other) = writers.insert(path.clone(), &job.file.path) {
+                return Err(Png2LvglError::Config(format!(
+                    "{} and {} would both write {}",
+                    other.display(),
+                    job.file.path.display(),
+                    path.display()
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Convert a single command-line input directly, anything else as a batch.
+///
+/// Returns the number of files that failed to convert.
+fn convert_jobs(args: &Args, jobs: &[Job], cache: Option<&Cache>) -> Result<usize> {
+    match jobs {
+        [job] if args.manifest.is_none() => convert(&job.args, &job.file, cache).map(|()| 0),
+        _ => convert_batch(args.jobs, jobs, cache),
+    }
+}
+
+/// Reject options that name a single output when converting several inputs.
+fn check_batch_options(args: &Args) -> Result<()> {
+    for (flag, used) in [
+        ("--output", args.output.is_some()),
+        ("--stdout", args.stdout),
+        ("--var-name", args.var_name.is_some()),
+    ] {
+        if used {
+            return Err(Png2LvglError::Config(format!(
+                "{flag} cannot be used with multiple input files"
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Convert every job, reporting all failures at the end.
+///
+/// Returns the number of files that failed to convert.
+fn convert_batch(
+    threads: Option<NonZeroUsize>,
+    jobs: &[Job],
+    cache: Option<&Cache>,
+) -> Result<usize> {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads.map_or(0, NonZeroUsize::get))
+        .build()
+        .map_err(|e| Png2LvglError::Config(format!("Cannot start worker threads: {e}")))?;
+    info!(
+        files = jobs.len(),
+        jobs = pool.current_num_threads(),
+        "Converting"
+    );
+
+    // Collected in input order, so the summary does not depend on scheduling
+    let results: Vec<Result<()>> = pool.install(|| {
+        jobs.par_iter()
+            .map(|job| logging::buffered(|| convert(&job.args, &job.file, cache)))
+            .collect()
+    });
+    let failures: Vec<(&Path, Png2LvglError)> = jobs
+        .iter()
+        .zip(results)
+        .filter_map(|(job, result)| result.err().map(|e| (job.file.path.as_path(), e)))
+        .collect();
+
+    info!(
+        "Converted {} of {} files",
+        jobs.len() - failures.len(),
+        jobs.len()
+    );
+    if failures.is_empty() {
+        return Ok(0);
+    }
+
+    for (path, e) in &failures {
+        error!("✗ {}: {e}", path.display());
     }
+    error!(
+        "{} of {} files failed to convert",
+        failures.len(),
+        jobs.len()
+    );
+    Ok(failures.len())
+}
 
-    info!(?args.input, "Loading image");
-    let img = image::open(&args.input)?;
+/// Convert one input file.
+#[instrument(skip_all, fields(input = %file.path.display()))]
+fn convert(args: &Args, file: &InputFile, cache: Option<&Cache>) -> Result<()> {
+    png2lvgl::validate_input_file(&file.path)?;
+
+    let (output, header_path) = output_paths(args, file)?;
+    let var_name = variable_name(args, &file.path, output.as_deref())?;
+
+    let outputs: Vec<&Path> = output
+        .iter()
+        .chain(&header_path)
+        .map(PathBuf::as_path)
+        .collect();
+    let cached = match (cache, outputs.first()) {
+        (Some(cache), Some(path)) => Some((cache, cache::key(args, &file.path, &var_name, path)?)),
+        _ => None,
+    };
+    if let Some((cache, key)) = cached
+        && cache.is_fresh(key, &outputs)
+    {
+        info!("= {} up to date", outputs[0].display());
+        return Ok(());
+    }
 
-    let (w, h) = img.dimensions();
-    validation::validate_dimensions(w, h)?;
+    info!(input = ?file.path, "Loading image");
+    let img = image::open(&file.path)?;
 
-    let fmt = match &args.format {
-        ColorFormat::Auto => format::detect(&img),
-        f => f.clone(),
+    let options = convert_options(args, var_name, &file.path, output.as_deref());
+    let fmt = options.resolve_format(&img);
+    warn_ignored_options(args, &fmt);
+
+    let data = if args.bin {
+        png2lvgl::convert_bin(&img, &options)?
+    } else {
+        png2lvgl::convert(&img, &options)?.into_bytes()
     };
 
-    if let Err(e) = format::validate(&img, &fmt) {
-        warn!("Format validation warning: {e}");
+    let header = match header_path {
+        Some(_) => Some(png2lvgl::convert_header(&img, &options)?),
+        None => None,
+    };
+
+    // Refuse before writing anything, so that a rejected header does not
+    // leave a new output file behind
+    if let Some(ref path) = output {
+        check_overwrite(path, &data, args.overwrite)?;
+    }
+    if let (Some(path), Some(header)) = (&header_path, &header) {
+        check_overwrite(path, header.as_bytes(), args.overwrite)?;
     }
 
-    if args.big_endian && !fmt.is_rgb565() {
-        warn!("--big-endian flag ignored: only applies to RGB565 formats");
+    if let Some(ref output_path) = output {
+        if write_file(output_path, &data)? {
+            let (w, h) = img.dimensions();
+            info!(
+                "✓ {w}x{h} → {} ({})",
+                output_path.display(),
+                fmt.lvgl_const(options.lvgl_version)
+            );
+        }
+    } else {
+        std::io::stdout().lock().write_all(&data)?;
     }
 
-    let var_name = output
+    if let (Some(path), Some(header)) = (&header_path, &header)
+        && write_file(path, header.as_bytes())?
+    {
+        info!("✓ {}", path.display());
+    }
+
+    if let Some((cache, key)) = cached {
+        cache.record(key, &outputs);
+    }
+
+    Ok(())
+}
+
+/// Output file (`None` for stdout) and companion header paths.
+///
+/// Existing files are only refused once it is known that they would change,
+/// see [`check_overwrite`].
+fn output_paths(args: &Args, file: &InputFile) -> Result<(Option<PathBuf>, Option<PathBuf>)> {
+    let output = output_path(args, file);
+
+    if let Some(ref path) = output {
+        if args.output_dir.is_some()
+            && let Some(parent) = path.parent()
+        {
+            std::fs::create_dir_all(parent)?;
+        }
+        check_output_path(path)?;
+    }
+
+    let header_path = output
         .as_ref()
-        .and_then(|p| p.file_stem())
-        .or_else(|| args.input.file_stem())
-        .and_then(|s| s.to_str())
-        .unwrap_or("image")
-        .replace('-', "_");
-
-    let source_filename = args
-        .input
-        .file_name()
-        .and_then(|s| s.to_str())
-        .unwrap_or("unknown.png");
-
-    let params = GenerateParams {
-        var_name: &var_name,
-        format: &fmt,
-        lvgl_version,
-        big_endian: args.big_endian,
-        source_file: source_filename,
-        output_file: output
-            .as_ref()
-            .and_then(|p| p.file_name())
-            .and_then(|s| s.to_str())
-            .unwrap_or("stdout"),
-    };
+        .filter(|_| args.header)
+        .map(|p| p.with_extension("h"));
+    if let Some(ref path) = header_path {
+        check_output_path(path)?;
+    }
+
+    Ok((output, header_path))
+}
 
+/// Output file for `file`, `None` when writing to stdout.
+fn output_path(args: &Args, file: &InputFile) -> Option<PathBuf> {
     if args.stdout {
-        let stdout = std::io::stdout();
-        let mut handle = stdout.lock();
-        codegen::generate(&img, &mut handle, &params)?;
-    } else {
-        let output_path = output.as_ref().expect("output path must be set");
-        let mut file = File::create(output_path)?;
+        return None;
+    }
+    let extension = if args.bin { "bin" } else { "c" };
+    Some(
+        args.output
+            .clone()
+            .unwrap_or_else(|| batch::output_path(file, args.output_dir.as_deref(), extension)),
+    )
+}
 
-        if let Err(e) = codegen::generate(&img, &mut file, &params) {
-            let _ = std::fs::remove_file(output_path);
-            return Err(e);
+/// Check that `path` can be written: its directory exists and the file name
+/// is usable.
+fn check_output_path(path: &Path) -> Result<()> {
+    debug!(?path, "Validating output path");
+
+    if let Some(parent) = path.parent()
+        && !parent.as_os_str().is_empty()
+        && (!parent.exists() || fs::metadata(parent).is_err())
+    {
+        return Err(ValidationError::OutputNotWritable {
+            path: parent.to_path_buf(),
         }
+        .into());
+    }
 
-        info!(
-            "✓ {w}x{h} → {} ({})",
-            output_path.display(),
-            fmt.lvgl_const(lvgl_version)
-        );
+    if let Some(name) = path.file_name() {
+        let name_str = name.to_string_lossy();
+        if name_str.contains('\0') || name_str.trim().is_empty() {
+            return Err(ValidationError::InvalidOutputFilename {
+                name: name_str.to_string(),
+            }
+            .into());
+        }
     }
 
     Ok(())
 }
+
+/// Refuse to replace an existing file with different contents unless
+/// `--overwrite` is given.
+fn check_overwrite(path: &Path, data: &[u8], overwrite: bool) -> Result<()> {
+    if overwrite || !path.exists() || fs::read(path).is_ok_and(|existing| existing == data) {
+        return Ok(());
+    }
+    Err(ValidationError::OutputExists {
+        path: path.to_path_buf(),
+    }
+    .into())
+}
+
+/// Write `data` to `path`, leaving a file that already holds exactly these
+/// bytes alone so that build systems do not see it as modified.
+///
+/// Returns whether the file was written.
+fn write_file(path: &Path, data: &[u8]) -> Result<bool> {
+    if fs::read(path).is_ok_and(|existing| existing == data) {
+        info!("= {} unchanged", path.display());
+        return Ok(false);
+    }
+    write_atomic(path, data)?;
+    Ok(true)
+}
+
+/// Write `data` to a temporary file next to `path` and rename it into place,
+/// so that readers see either the old or the complete new file, even if
+/// the process dies halfway.
+fn write_atomic(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temp = path.with_file_name(format!(
+        ".{name}.{}.{}.tmp",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let result = fs::File::create(&temp)
+        .and_then(|mut file| {
+            file.write_all(data)?;
+            file.sync_all()
+        })
+        .and_then(|()| fs::rename(&temp, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
@@ -0,0 +1,256 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Fabian Schmieder
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use clap::ValueEnum;
+use serde::Deserialize;
+use tracing::debug;
+
+use crate::Args;
+use crate::batch::{InputFile, Job};
+use png2lvgl::{LvglVersion, Png2LvglError, Result};
+
+/// An `assets.toml` listing every image of a project.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Manifest {
+    /// Options shared by all images.
+    #[serde(default)]
+    defaults: Options,
+
+    #[serde(default, rename = "image")]
+    images: Vec<Options>,
+}
+
+/// Keys of `[defaults]` and `[[image]]`, named after the command-line options.
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct Options {
+    input: Option<PathBuf>,
+    output: Option<PathBuf>,
+    output_dir: Option<PathBuf>,
+    bin: Option<bool>,
+    header: Option<bool>,
+    var_name: Option<String>,
+    var_prefix: Option<String>,
+    overwrite: Option<bool>,
+    format: Option<String>,
+    lvgl: Option<String>,
+    big_endian: Option<bool>,
+    quantize: Option<String>,
+    dither: Option<String>,
+    chroma_key: Option<String>,
+    all_depths: Option<bool>,
+    alpha_source: Option<String>,
+    stride_align: Option<usize>,
+    premultiply: Option<bool>,
+    compress: Option<String>,
+}
+
+impl Options {
+    /// Override the options set in this table; paths are relative to `base`.
+    fn apply(&self, args: &mut Args, base: &Path) -> std::result::Result<(), String> {
+        if let Some(path) = &self.output {
+            args.output = Some(base.join(path));
+        }
+        if let Some(dir) = &self.output_dir {
+            args.output_dir = Some(base.join(dir));
+        }
+        if let Some(name) = &self.var_name {
+            args.var_name = Some(name.clone());
+        }
+        if let Some(prefix) = &self.var_prefix {
+            args.var_prefix.clone_from(prefix);
+        }
+
+        for (flag, value) in [
+            (&mut args.bin, self.bin),
+            (&mut args.header, self.header),
+            (&mut args.overwrite, self.overwrite),
+            (&mut args.big_endian, self.big_endian),
+            (&mut args.all_depths, self.all_depths),
+            (&mut args.premultiply, self.premultiply),
+        ] {
+            if let Some(value) = value {
+                *flag = value;
+            }
+        }
+
+        if let Some(value) = &self.format {
+            args.format = parse_enum("format", value)?;
+        }
+        if let Some(value) = &self.lvgl {
+            args.lvgl_v8 = matches!(parse_enum("lvgl", value)?, LvglVersion::V8);
+            args.lvgl_v9 = !args.lvgl_v8;
+        }
+        if let Some(value) = &self.quantize {
+            args.quantize = parse_enum("quantize", value)?;
+        }
+        if let Some(value) = &self.dither {
+            args.dither = parse_enum("dither", value)?;
+        }
+        if let Some(value) = &self.chroma_key {
+            let color = png2lvgl::parse_hex_color(value).map_err(|e| format!("chroma-key: {e}"))?;
+            args.chroma_key = Some(color);
+        }
+        if let Some(value) = &self.alpha_source {
+            args.alpha_source = parse_enum("alpha-source", value)?;
+        }
+        if let Some(value) = self.stride_align {
+            args.stride_align = png2lvgl::parse_stride_align(&value.to_string())
+                .map_err(|e| format!("stride-align: {e}"))?;
+        }
+        if let Some(value) = &self.compress {
+            args.compress = parse_enum("compress", value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Read the manifest at `path` and turn every `[[image]]` into a job.
+///
+/// Options are layered: command line, then `[defaults]`, then the entry.
+/// Relative paths are resolved against the manifest's directory.
+pub fn load(path: &Path, args: &Args) -> Result<Vec<Job>> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| config_error(path, &format!("Cannot read manifest: {e}")))?;
+    let manifest: Manifest =
+        toml::from_str(&text).map_err(|e| config_error(path, e.to_string().trim_end()))?;
+    let base = path.parent().unwrap_or_else(|| Path::new(""));
+
+    if manifest.defaults.input.is_some() {
+        return Err(config_error(
+            path,
+            "[defaults]: input is only allowed in [[image]]",
+        ));
+    }
+    if manifest.images.is_empty() {
+        return Err(config_error(path, "No [[image]] entries"));
+    }
+
+    let mut defaults = args.clone();
+    manifest
+        .defaults
+        .apply(&mut defaults, base)
+        .map_err(|e| config_error(path, &format!("[defaults]: {e}")))?;
+
+    let jobs = manifest
+        .images
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| {
+            let Some(input) = &entry.input else {
+                return Err(config_error(
+                    path,
+                    &format!("image {}: missing input", i + 1),
+                ));
+            };
+            let mut args = defaults.clone();
+            entry
+                .apply(&mut args, base)
+                .and_then(|()| check_conflicts(&args))
+                .map_err(|e| {
+                    config_error(path, &format!("image {} ({}): {e}", i + 1, input.display()))
+                })?;
+
+            let relative = if input.is_relative() {
+                input.clone()
+            } else {
+                input.file_name().map(PathBuf::from).unwrap_or_default()
+            };
+            Ok(Job {
+                args,
+                file: InputFile {
+                    path: base.join(input),
+                    relative,
+                },
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    debug!(manifest = %path.display(), images = jobs.len(), "Loaded manifest");
+    Ok(jobs)
+}
+
+/// Combinations clap rejects on the command line.
+fn check_conflicts(args: &Args) -> std::result::Result<(), String> {
+    if args.all_depths && !args.lvgl_v8 {
+        return Err("all-depths requires lvgl = v8".to_string());
+    }
+    if args.bin && args.all_depths {
+        return Err("bin cannot be combined with all-depths".to_string());
+    }
+    if args.bin && args.header {
+        return Err("bin cannot be combined with header".to_string());
+    }
+    Ok(())
+}
+
+fn parse_enum<T: ValueEnum>(key: &str, value: &str) -> std::result::Result<T, String> {
+    T::from_str(value, false).map_err(|_| {
+        let expected: Vec<String> = T::value_variants()
+            .iter()
+            .filter_map(ValueEnum::to_possible_value)
+            .map(|v| v.get_name().to_string())
+            .collect();
+        format!(
+            "{key}: invalid value '{value}', expected one of: {}",
+            expected.join(", ")
+        )
+    })
+}
+
+fn config_error(path: &Path, message: &str) -> Png2LvglError {
+    Png2LvglError::Config(format!("{}: {message}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+    use png2lvgl::ColorFormat;
+
+    fn parse(text: &str) -> std::result::Result<Manifest, toml::de::Error> {
+        toml::from_str(text)
+    }
+
+    #[test]
+    fn entries_override_defaults() {
+        let manifest = parse(
+            r#"
+            [defaults]
+            format = "indexed4"
+            var-prefix = "img_"
+            big-endian = true
+
+            [[image]]
+            input = "logo.png"
+            format = "alpha8"
+            lvgl = "v8"
+            "#,
+        )
+        .unwrap();
+
+        let mut args = Args::parse_from(["png2lvgl", "--manifest", "assets.toml"]);
+        manifest.defaults.apply(&mut args, Path::new("")).unwrap();
+        manifest.images[0].apply(&mut args, Path::new("")).unwrap();
+
+        assert!(matches!(args.format, ColorFormat::Alpha8));
+        assert_eq!(args.var_prefix, "img_");
+        assert!(args.big_endian);
+        assert!(args.lvgl_v8 && !args.lvgl_v9);
+    }
+
+    #[test]
+    fn rejects_unknown_keys_and_values() {
+        assert!(parse("[[image]]\ninput = \"a.png\"\nfromat = \"l8\"").is_err());
+        assert!(parse("[default]\nformat = \"l8\"").is_err());
+
+        let manifest = parse("[[image]]\ninput = \"a.png\"\ndither = \"fancy\"").unwrap();
+        let mut args = Args::parse_from(["png2lvgl", "--manifest", "assets.toml"]);
+        assert!(manifest.images[0].apply(&mut args, Path::new("")).is_err());
+    }
+}
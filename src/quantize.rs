@@ -0,0 +1,439 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Fabian Schmieder
+
+use std::collections::HashMap;
+
+use image::{Rgba, RgbaImage};
+use tracing::debug;
+
+/// Maximum number of k-means refinement passes.
+const KMEANS_MAX_ITERATIONS: usize = 16;
+
+/// Depth of the color tree used by the octree quantizer (one level per bit).
+const OCTREE_DEPTH: usize = 8;
+
+/// Children per color tree node (one bit from each RGBA channel).
+const OCTREE_CHILDREN: usize = 16;
+
+/// Peak sample value used for PSNR.
+const MAX_SAMPLE: f64 = 255.0;
+
+#[derive(Copy, Clone, Debug, Default, Hash, clap::ValueEnum)]
+pub enum QuantizeMethod {
+    #[default]
+    MedianCut,
+    Octree,
+    KMeans,
+}
+
+impl QuantizeMethod {
+    /// Name used in the C file header comment.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::MedianCut => "median-cut",
+            Self::Octree => "octree",
+            Self::KMeans => "k-means",
+        }
+    }
+}
+
+/// A unique color together with the number of pixels using it.
+#[derive(Clone, Copy)]
+struct Bucket {
+    color: Rgba<u8>,
+    count: u64,
+}
+
+/// Reduce the image to at most `max_colors` representative colors.
+pub fn quantize(rgba: &RgbaImage, max_colors: usize, method: QuantizeMethod) -> Vec<Rgba<u8>> {
+    let buckets = histogram(rgba);
+    debug!(
+        unique_colors = buckets.len(),
+        max_colors,
+        ?method,
+        "Quantizing colors"
+    );
+
+    match method {
+        QuantizeMethod::MedianCut => median_cut(&buckets, max_colors),
+        QuantizeMethod::Octree => octree(&buckets, max_colors),
+        QuantizeMethod::KMeans => k_means(&buckets, max_colors),
+    }
+}
+
+/// Map every pixel to its nearest palette entry, in row-major order.
+pub fn remap(rgba: &RgbaImage, palette: &[Rgba<u8>]) -> Vec<u8> {
+    let mut cache: HashMap<Rgba<u8>, u8> = HashMap::new();
+    rgba.pixels()
+        .map(|pixel| {
+            *cache
+                .entry(*pixel)
+                .or_insert_with(|| nearest(palette, *pixel))
+        })
+        .collect()
+}
+
+/// Mean squared error per channel between the image and its palette indices.
+pub fn mse(rgba: &RgbaImage, palette: &[Rgba<u8>], indices: &[u8]) -> f64 {
+    let squared_error: u64 = rgba
+        .pixels()
+        .zip(indices)
+        .map(|(pixel, &index)| distance(*pixel, palette[usize::from(index)]))
+        .sum();
+
+    #[allow(clippy::cast_precision_loss)]
+    let mse = squared_error as f64 / rgba.as_raw().len().max(1) as f64;
+    mse
+}
+
+/// Peak signal-to-noise ratio in dB for a mean squared error.
+pub fn psnr(mse: f64) -> f64 {
+    10.0 * (MAX_SAMPLE * MAX_SAMPLE / mse).log10()
+}
+
+/// Index of the palette entry closest to `color`.
+pub fn nearest(palette: &[Rgba<u8>], color: Rgba<u8>) -> u8 {
+    let index = palette
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, entry)| distance(**entry, color))
+        .map_or(0, |(i, _)| i);
+
+    #[allow(clippy::cast_possible_truncation)]
+    let index = index as u8;
+    index
+}
+
+/// Squared Euclidean distance between two colors in RGBA space.
+fn distance(a: Rgba<u8>, b: Rgba<u8>) -> u64 {
+    a.0.iter()
+        .zip(b.0.iter())
+        .map(|(&x, &y)| u64::from(x.abs_diff(y)).pow(2))
+        .sum()
+}
+
+fn histogram(rgba: &RgbaImage) -> Vec<Bucket> {
+    let mut counts: HashMap<Rgba<u8>, u64> = HashMap::new();
+    for pixel in rgba.pixels() {
+        *counts.entry(*pixel).or_default() += 1;
+    }
+
+    let mut buckets: Vec<Bucket> = counts
+        .into_iter()
+        .map(|(color, count)| Bucket { color, count })
+        .collect();
+    // HashMap iteration order is random; sort for deterministic output.
+    buckets.sort_unstable_by_key(|bucket| bucket.color.0);
+    buckets
+}
+
+/// Count-weighted mean of accumulated channel sums.
+fn mean_color(sum: [u64; 4], count: u64) -> Rgba<u8> {
+    let count = count.max(1);
+    #[allow(clippy::cast_possible_truncation)]
+    Rgba(sum.map(|channel| ((channel + count / 2) / count) as u8))
+}
+
+fn bucket_mean(buckets: &[Bucket]) -> Rgba<u8> {
+    let mut sum = [0u64; 4];
+    let mut count = 0;
+    for bucket in buckets {
+        for (acc, &channel) in sum.iter_mut().zip(bucket.color.0.iter()) {
+            *acc += u64::from(channel) * bucket.count;
+        }
+        count += bucket.count;
+    }
+    mean_color(sum, count)
+}
+
+// ---------------------------------------------------------------------------
+// Median cut
+// ---------------------------------------------------------------------------
+
+fn median_cut(buckets: &[Bucket], max_colors: usize) -> Vec<Rgba<u8>> {
+    let mut boxes: Vec<Vec<Bucket>> = vec![buckets.to_vec()];
+
+    while boxes.len() < max_colors {
+        // Split the box with the widest channel range at its weighted median
+        let Some((index, channel, _)) = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| b.len() > 1)
+            .map(|(i, b)| {
+                let (channel, range) = widest_channel(b);
+                (i, channel, range)
+            })
+            .max_by_key(|&(_, _, range)| range)
+        else {
+            break;
+        };
+
+        let mut lower = boxes.swap_remove(index);
+        lower.sort_unstable_by_key(|bucket| (bucket.color[channel], bucket.color.0));
+
+        let total: u64 = lower.iter().map(|bucket| bucket.count).sum();
+        let mut seen = 0;
+        let split = lower
+            .iter()
+            .position(|bucket| {
+                seen += bucket.count;
+                seen * 2 >= total
+            })
+            .map_or(1, |i| i + 1)
+            .clamp(1, lower.len() - 1);
+
+        let upper = lower.split_off(split);
+        boxes.push(lower);
+        boxes.push(upper);
+    }
+
+    boxes.iter().map(|b| bucket_mean(b)).collect()
+}
+
+/// Returns the channel with the largest value range and that range.
+fn widest_channel(buckets: &[Bucket]) -> (usize, u8) {
+    (0..4)
+        .map(|channel| {
+            let (min, max) = buckets.iter().fold((u8::MAX, u8::MIN), |(lo, hi), b| {
+                (lo.min(b.color[channel]), hi.max(b.color[channel]))
+            });
+            (channel, max - min)
+        })
+        .max_by_key(|&(channel, range)| (range, std::cmp::Reverse(channel)))
+        .unwrap_or((0, 0))
+}
+
+// ---------------------------------------------------------------------------
+// Octree
+// ---------------------------------------------------------------------------
+
+#[derive(Default)]
+struct OctreeNode {
+    sum: [u64; 4],
+    count: u64,
+    children: [Option<usize>; OCTREE_CHILDREN],
+    leaf: bool,
+}
+
+impl OctreeNode {
+    fn add(&mut self, bucket: &Bucket) {
+        for (acc, &channel) in self.sum.iter_mut().zip(bucket.color.0.iter()) {
+            *acc += u64::from(channel) * bucket.count;
+        }
+        self.count += bucket.count;
+    }
+}
+
+fn octree(buckets: &[Bucket], max_colors: usize) -> Vec<Rgba<u8>> {
+    let mut nodes = vec![OctreeNode::default()];
+    // Inner nodes per tree level, candidates for folding
+    let mut levels: Vec<Vec<usize>> = vec![Vec::new(); OCTREE_DEPTH];
+    levels[0].push(0);
+
+    for bucket in buckets {
+        let mut node = 0;
+        for level in 0..OCTREE_DEPTH {
+            nodes[node].add(bucket);
+            let child = octree_child(bucket.color, level);
+            if nodes[node].children[child].is_none() {
+                let created = nodes.len();
+                nodes.push(OctreeNode {
+                    leaf: level + 1 == OCTREE_DEPTH,
+                    ..OctreeNode::default()
+                });
+                nodes[node].children[child] = Some(created);
+                if level + 1 < OCTREE_DEPTH {
+                    levels[level + 1].push(created);
+                }
+            }
+            node = nodes[node].children[child].expect("child node exists");
+        }
+        nodes[node].add(bucket);
+    }
+
+    // Fold the least-used deepest nodes into leaves until the palette fits.
+    // The root is never folded, and on the first level folds that would
+    // leave fewer colors than wanted are skipped; the rest is merged below.
+    let mut leaves = buckets.len();
+    'fold: for (depth, level) in levels.iter_mut().enumerate().skip(1).rev() {
+        level.sort_by_key(|&n| nodes[n].count);
+        for &n in level.iter() {
+            if leaves <= max_colors {
+                break 'fold;
+            }
+            let children = nodes[n].children.iter().flatten().count();
+            if depth == 1 && leaves + 1 - children < max_colors {
+                continue;
+            }
+            nodes[n].leaf = true;
+            leaves = leaves + 1 - children;
+        }
+    }
+
+    let mut colors = Vec::with_capacity(leaves);
+    let mut stack = vec![0];
+    while let Some(n) = stack.pop() {
+        let node = &nodes[n];
+        if node.leaf {
+            colors.push((node.sum, node.count));
+        } else {
+            stack.extend(node.children.iter().rev().flatten());
+        }
+    }
+    merge_least_used(&mut colors, max_colors);
+
+    colors
+        .into_iter()
+        .map(|(sum, count)| mean_color(sum, count))
+        .collect()
+}
+
+/// Merge the least-used color into its nearest neighbour until at most
+/// `max_colors` remain.
+fn merge_least_used(colors: &mut Vec<([u64; 4], u64)>, max_colors: usize) {
+    while colors.len() > max_colors.max(1) {
+        let Some(least) = (0..colors.len()).min_by_key(|&i| colors[i].1) else {
+            break;
+        };
+        let (sum, count) = colors.remove(least);
+        let color = mean_color(sum, count);
+        let Some(target) = (0..colors.len())
+            .min_by_key(|&i| distance(mean_color(colors[i].0, colors[i].1), color))
+        else {
+            break;
+        };
+
+        let (target_sum, target_count) = &mut colors[target];
+        for (acc, channel) in target_sum.iter_mut().zip(sum) {
+            *acc += channel;
+        }
+        *target_count += count;
+    }
+}
+
+/// Child slot for `color` at `level`: one bit of each channel, MSB first.
+fn octree_child(color: Rgba<u8>, level: usize) -> usize {
+    let shift = 7 - level;
+    color.0.iter().fold(0, |acc, &channel| {
+        (acc << 1) | usize::from((channel >> shift) & 1)
+    })
+}
+
+// ---------------------------------------------------------------------------
+// K-means
+// ---------------------------------------------------------------------------
+
+fn k_means(buckets: &[Bucket], max_colors: usize) -> Vec<Rgba<u8>> {
+    // Seed with median cut for a deterministic, well-spread start
+    let mut centroids = median_cut(buckets, max_colors);
+    let mut assignment = vec![usize::MAX; buckets.len()];
+
+    for iteration in 0..KMEANS_MAX_ITERATIONS {
+        let mut changed = false;
+        for (bucket, slot) in buckets.iter().zip(assignment.iter_mut()) {
+            let index = usize::from(nearest(&centroids, bucket.color));
+            if *slot != index {
+                *slot = index;
+                changed = true;
+            }
+        }
+        if !changed {
+            debug!(iteration, "K-means converged");
+            break;
+        }
+
+        let mut sums = vec![([0u64; 4], 0u64); centroids.len()];
+        for (bucket, &index) in buckets.iter().zip(assignment.iter()) {
+            let (sum, count) = &mut sums[index];
+            for (acc, &channel) in sum.iter_mut().zip(bucket.color.0.iter()) {
+                *acc += u64::from(channel) * bucket.count;
+            }
+            *count += bucket.count;
+        }
+        for (centroid, (sum, count)) in centroids.iter_mut().zip(sums) {
+            if count > 0 {
+                *centroid = mean_color(sum, count);
+            }
+        }
+    }
+
+    centroids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const METHODS: [QuantizeMethod; 3] = [
+        QuantizeMethod::MedianCut,
+        QuantizeMethod::Octree,
+        QuantizeMethod::KMeans,
+    ];
+
+    /// 256 distinct colors spread over the whole RGB cube.
+    fn many_colors() -> RgbaImage {
+        RgbaImage::from_fn(16, 16, |x, y| {
+            #[allow(clippy::cast_possible_truncation)]
+            let (x, y) = (x as u8, y as u8);
+            Rgba([x * 17, y * 17, (x ^ y) * 17, 0xFF])
+        })
+    }
+
+    /// Reds on the left half, blues on the right half.
+    fn two_clusters() -> RgbaImage {
+        RgbaImage::from_fn(8, 4, |x, y| {
+            #[allow(clippy::cast_possible_truncation)]
+            let shade = (x % 4 * 4 + y) as u8 * 2;
+            if x < 4 {
+                Rgba([0xE0 + shade, shade, shade, 0xFF])
+            } else {
+                Rgba([shade, shade, 0xE0 + shade, 0xFF])
+            }
+        })
+    }
+
+    #[test]
+    fn every_method_fills_the_palette() {
+        let rgba = many_colors();
+        for method in METHODS {
+            for max_colors in [2, 4, 16] {
+                let palette = quantize(&rgba, max_colors, method);
+                assert_eq!(
+                    palette.len(),
+                    max_colors,
+                    "{} to {max_colors}",
+                    method.name()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn every_method_separates_clusters() {
+        let rgba = two_clusters();
+        for method in METHODS {
+            let palette = quantize(&rgba, 2, method);
+            assert!(
+                palette.iter().any(|c| c[0] > 0xC0 && c[2] < 0x40),
+                "{}: {palette:?}",
+                method.name()
+            );
+            assert!(
+                palette.iter().any(|c| c[2] > 0xC0 && c[0] < 0x40),
+                "{}: {palette:?}",
+                method.name()
+            );
+        }
+    }
+
+    #[test]
+    fn exact_palette_has_no_error() {
+        let rgba = two_clusters();
+        let palette: Vec<Rgba<u8>> = histogram(&rgba).iter().map(|b| b.color).collect();
+        let indices = remap(&rgba, &palette);
+
+        assert!(mse(&rgba, &palette, &indices) < f64::EPSILON);
+        assert_eq!(palette[usize::from(indices[0])], *rgba.get_pixel(0, 0));
+    }
+}
@@ -18,6 +18,11 @@ const MAX_FILE_SIZE: u64 = 100 * 1024 * 1024;
 /// PNG magic bytes.
 const PNG_HEADER: &[u8; 8] = b"\x89PNG\r\n\x1a\n";
 
+/// Check that `path` is a readable PNG of acceptable size.
+///
+/// # Errors
+///
+/// Fails if the file is missing, unreadable, too large or not a PNG.
 pub fn validate_input_file(path: &Path) -> Result<()> {
     debug!(?path, "Validating input file");
 
@@ -56,6 +61,11 @@ pub fn validate_input_file(path: &Path) -> Result<()> {
     Ok(())
 }
 
+/// Check that an image size is within the supported range.
+///
+/// # Errors
+///
+/// Fails if either side is zero or larger than 8192 pixels.
 pub fn validate_dimensions(width: u32, height: u32) -> Result<()> {
     debug!(width, height, "Validating dimensions");
 
@@ -82,36 +92,3 @@ pub fn validate_dimensions(width: u32, height: u32) -> Result<()> {
 
     Ok(())
 }
-
-pub fn validate_output_path(path: &Path, overwrite: bool) -> Result<()> {
-    debug!(?path, overwrite, "Validating output path");
-
-    if path.exists() && !overwrite {
-        return Err(ValidationError::OutputExists {
-            path: path.to_path_buf(),
-        }
-        .into());
-    }
-
-    if let Some(parent) = path.parent()
-        && !parent.as_os_str().is_empty()
-        && (!parent.exists() || fs::metadata(parent).is_err())
-    {
-        return Err(ValidationError::OutputNotWritable {
-            path: parent.to_path_buf(),
-        }
-        .into());
-    }
-
-    if let Some(name) = path.file_name() {
-        let name_str = name.to_string_lossy();
-        if name_str.contains('\0') || name_str.trim().is_empty() {
-            return Err(ValidationError::InvalidOutputFilename {
-                name: name_str.to_string(),
-            }
-            .into());
-        }
-    }
-
-    Ok(())
-}
@@ -0,0 +1,129 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Fabian Schmieder
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use tracing::{debug, error, info};
+
+use crate::batch::{self, Job};
+use crate::cache::Cache;
+use crate::{Args, manifest};
+
+/// How often the sources are checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Sources must be left alone this long before converting, so that an export
+/// touching a file several times (or many files at once) converts only once.
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Modification time and size of every source file (`None` if missing).
+type Snapshot = BTreeMap<PathBuf, Option<(SystemTime, u64)>>;
+
+/// Convert the inputs, then keep converting the ones that change until the
+/// process is stopped.
+///
+/// Errors are logged and the next change is waited for, so a half-exported
+/// PNG or a typo in the manifest does not end the session.
+pub fn run(args: &Args) -> ! {
+    info!("Watching for changes (press Ctrl+C to stop)");
+
+    let cache = args.cache.as_deref().map(Cache::load);
+    let mut converted = Snapshot::new();
+    let mut seen = Snapshot::new();
+    let mut quiet_since = Instant::now();
+    loop {
+        let current = snapshot(&sources(args));
+        if current != seen {
+            seen = current;
+            quiet_since = Instant::now();
+        } else if seen != converted && quiet_since.elapsed() >= DEBOUNCE {
+            let changed: BTreeSet<&Path> = seen
+                .iter()
+                .filter(|(path, state)| converted.get(*path) != Some(*state))
+                .map(|(path, _)| path.as_path())
+                .collect();
+            if !changed.is_empty() {
+                convert_changed(args, &changed, cache.as_ref());
+            }
+            converted.clone_from(&seen);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Convert the jobs whose input changed, or all of them if the manifest did.
+fn convert_changed(args: &Args, changed: &BTreeSet<&Path>, cache: Option<&Cache>) {
+    debug!(count = changed.len(), "Sources changed");
+    let manifest_changed = args
+        .manifest
+        .as_deref()
+        .is_some_and(|path| changed.contains(path));
+
+    let jobs = match crate::collect_jobs(args) {
+        Ok(jobs) => jobs,
+        Err(e) => {
+            error!("{e}");
+            return;
+        }
+    };
+    let jobs: Vec<Job> = jobs
+        .into_iter()
+        .filter(|job| manifest_changed || changed.contains(job.file.path.as_path()))
+        .collect();
+
+    match jobs.as_slice() {
+        [] => {}
+        [job] => {
+            if let Err(e) = crate::convert(&job.args, &job.file, cache) {
+                error!("✗ {}: {e}", job.file.path.display());
+            }
+        }
+        _ => {
+            if let Err(e) = crate::convert_batch(args.jobs, &jobs, cache) {
+                error!("{e}");
+            }
+        }
+    }
+
+    if let Some(cache) = cache
+        && let Err(e) = cache.save()
+    {
+        error!("Cannot save cache: {e}");
+    }
+    info!("Waiting for changes");
+}
+
+/// Files whose changes trigger a conversion.
+///
+/// Inputs are expanded again on every poll so that new files in watched
+/// directories are picked up; an unreadable manifest is watched on its own.
+fn sources(args: &Args) -> Vec<PathBuf> {
+    if let Some(path) = &args.manifest {
+        let jobs = manifest::load(path, args).unwrap_or_default();
+        return std::iter::once(path.clone())
+            .chain(jobs.into_iter().map(|job| job.file.path))
+            .collect();
+    }
+
+    args.inputs
+        .iter()
+        .flat_map(|input| batch::expand(input, args.recursive).unwrap_or_default())
+        .map(|file| file.path)
+        .collect()
+}
+
+fn snapshot(paths: &[PathBuf]) -> Snapshot {
+    paths
+        .iter()
+        .map(|path| {
+            let state = fs::metadata(path)
+                .and_then(|meta| Ok((meta.modified()?, meta.len())))
+                .ok();
+            (path.clone(), state)
+        })
+        .collect()
+}